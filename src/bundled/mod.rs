@@ -0,0 +1,176 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Stylesheets shipped inside mllt, which themes opt into by
+//! name with `{{{bundled "normalize"}}}`.
+
+use color_eyre::eyre::{Context as _, Result};
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::url::BaseUrl;
+
+/// Folder of the output that linked stylesheets are written to.
+const DIR: &str = "_bundled";
+
+/// A stylesheet in the built-in collection.
+pub struct Stylesheet {
+    /// What themes ask for it by.
+    pub name: &'static str,
+
+    /// Version of the upstream stylesheet, or of ours.
+    /// Part of the linked file's name, for cache busting.
+    pub version: &'static str,
+
+    pub css: &'static str,
+}
+
+impl Stylesheet {
+    /// Where the stylesheet is written to when linked,
+    /// relative to the output folder.
+    fn path(&self) -> String {
+        format!("{DIR}/{}-{}.css", self.name, self.version)
+    }
+}
+
+pub static STYLESHEETS: &[Stylesheet] = &[
+    // https://necolas.github.io/normalize.css/
+    Stylesheet {
+        name: "normalize",
+        version: "8.0.1",
+        css: include_str!("normalize-8.0.1.min.css"),
+    },
+    // A small modern reset: border-box sizing, no default margins,
+    // responsive media and reduced motion when asked for.
+    Stylesheet {
+        name: "reset",
+        version: "1.0.0",
+        css: include_str!("reset-1.0.0.min.css"),
+    },
+];
+
+/// How the `bundled` helper includes stylesheets in a page.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Bundling {
+    /// In a `<style>` tag.
+    #[default]
+    Inline,
+    /// As a `<link>` to a file under `_bundled/` in the output folder.
+    Link,
+}
+
+/// Look a stylesheet up by name.
+pub fn find(name: &str) -> Option<&'static Stylesheet> {
+    STYLESHEETS.iter().find(|s| s.name == name)
+}
+
+/// Names of every bundled stylesheet, for error messages.
+pub fn names() -> String {
+    STYLESHEETS
+        .iter()
+        .map(|s| format!("\"{}\"", s.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Every stylesheet's CSS keyed by name, for the `_bundled` variable.
+pub fn context() -> serde_json::Value {
+    STYLESHEETS
+        .iter()
+        .map(|s| (s.name.to_owned(), s.css.into()))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Markup including `stylesheets` in a page.
+pub fn markup(stylesheets: &[&Stylesheet], bundling: Bundling, base: &BaseUrl) -> String {
+    match bundling {
+        Bundling::Inline => {
+            let css: Vec<&str> = stylesheets.iter().map(|s| s.css).collect();
+            format!("<style>{}</style>", css.join("\n"))
+        }
+        Bundling::Link => stylesheets
+            .iter()
+            .map(|s| format!(r#"<link rel="stylesheet" href="{}">"#, base.rel(&s.path())))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// Write `stylesheets` into `dst` to be linked to, returning
+/// their paths relative to it.
+pub fn write(dst: &Path, stylesheets: &[&Stylesheet]) -> Result<HashSet<PathBuf>> {
+    let mut written = HashSet::new();
+    for stylesheet in stylesheets {
+        std::fs::create_dir_all(dst.join(DIR))?;
+        let path = dst.join(stylesheet.path());
+        if std::fs::read_to_string(&path).ok().as_deref() != Some(stylesheet.css) {
+            std::fs::write(&path, stylesheet.css)
+                .context(format!("Error writing \"{}\"", path.display()))?;
+            debug!("Wrote: {}", path.display());
+        }
+        written.insert(stylesheet.path().into());
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stylesheets() -> [&'static Stylesheet; 2] {
+        [find("normalize").unwrap(), find("reset").unwrap()]
+    }
+
+    #[test]
+    fn inline_markup() {
+        let [normalize, reset] = stylesheets();
+        let markup = markup(&[reset, normalize], Bundling::Inline, &BaseUrl::parse("/"));
+        assert_eq!(markup, format!("<style>{}\n{}</style>", reset.css, normalize.css));
+    }
+
+    #[test]
+    fn link_markup() {
+        let markup = markup(&stylesheets(), Bundling::Link, &BaseUrl::parse("/blog/"));
+        assert_eq!(
+            markup,
+            "<link rel=\"stylesheet\" href=\"/blog/_bundled/normalize-8.0.1.css\">\n\
+             <link rel=\"stylesheet\" href=\"/blog/_bundled/reset-1.0.0.css\">"
+        );
+    }
+
+    #[test]
+    fn only_the_given_stylesheets_are_written() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(write(dir.path(), &[]).unwrap().is_empty());
+        assert!(!dir.path().join(DIR).exists());
+
+        let reset = find("reset").unwrap();
+        let written = write(dir.path(), &[reset]).unwrap();
+        assert_eq!(written, HashSet::from([PathBuf::from("_bundled/reset-1.0.0.css")]));
+        let files: Vec<_> = std::fs::read_dir(dir.path().join(DIR)).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("_bundled/reset-1.0.0.css")).unwrap(),
+            reset.css
+        );
+    }
+}
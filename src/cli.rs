@@ -16,7 +16,7 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -41,31 +41,18 @@ pub enum Command {
     /// Render the site to static HTML.
     #[command(alias = "b")]
     Build {
-        /// Enable strict mode in the handlebars parser. This causes
-        /// missing or unknown values to produce hard errors instead of
-        /// empty strings.
+        /// Delete files in the output folder that this build didn't
+        /// produce, except those matching `protect` in the config file.
         #[arg(long, action = clap::ArgAction::SetTrue)]
-        strict: Option<bool>,
+        prune: Option<bool>,
 
-        /// Overrides the output folder path specified in the config file.
-        #[arg(short, long)]
-        output: Option<PathBuf>,
-
-        /// Overrides the content folder path specified in the config file.
-        #[arg(long)]
-        content: Option<PathBuf>,
-
-        /// Overrides the theme folder path specified in the config file.
+        /// List the files that pruning would delete, building into
+        /// a temporary folder so the output folder isn't touched.
         #[arg(long)]
-        theme: Option<PathBuf>,
+        dry_run: bool,
 
-        /// Overrides the assets folder path specified in the config file.
-        #[arg(long)]
-        assets: Option<PathBuf>,
-
-        /// Path to the config file.
-        #[arg(short, long, default_value = "./mllt.toml")]
-        config: PathBuf,
+        #[command(flatten)]
+        site: SiteArgs,
     },
 
     /// Start a local development server.
@@ -75,27 +62,8 @@ pub enum Command {
         #[arg(short, long, default_value = "1313")]
         port: u16,
 
-        /// Enable strict mode in the handlebars parser. This causes
-        /// missing or unknown values to produce hard errors instead of
-        /// empty strings.
-        #[arg(long, action = clap::ArgAction::SetTrue)]
-        strict: Option<bool>,
-
-        /// Overrides the content folder path specified in the config file.
-        #[arg(long)]
-        content: Option<PathBuf>,
-
-        /// Overrides the theme folder path specified in the config file.
-        #[arg(long)]
-        theme: Option<PathBuf>,
-
-        /// Overrides the assets folder path specified in the config file.
-        #[arg(long)]
-        assets: Option<PathBuf>,
-
-        /// Path to the config file.
-        #[arg(short, long, default_value = "./mllt.toml")]
-        config: PathBuf,
+        #[command(flatten)]
+        site: SiteArgs,
     },
 
     /// Create a new mllt site at the given path.
@@ -106,9 +74,97 @@ pub enum Command {
         #[arg(long)]
         force: bool,
 
+        /// Starter theme to create the site with. See `mllt theme list`.
+        #[arg(long, default_value = crate::new::DEFAULT_THEME)]
+        theme: String,
+
+        /// Copy a template project instead of a starter theme: a
+        /// folder with an mllt.toml, or a git URL to clone.
+        #[arg(long, conflicts_with = "theme")]
+        from: Option<String>,
+
+        /// The new site's base URL, written to mllt.toml.
+        #[arg(long)]
+        base_url: Option<String>,
+
+        /// Don't ask how to set up the site, use the
+        /// defaults and whatever is given on the command line.
+        #[arg(long, short)]
+        yes: bool,
+
         /// The name of the project, which is the path to the
         /// project root.
         #[arg()]
         base_path: PathBuf
     },
+
+    /// Work with themes.
+    Theme {
+        #[command(subcommand)]
+        command: ThemeCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ThemeCommand {
+    /// List the starter themes built into mllt.
+    #[command(alias = "ls")]
+    List,
+}
+
+impl Command {
+    /// The site options passed to this subcommand, if it takes any.
+    pub fn site_args(&self) -> Option<&SiteArgs> {
+        match self {
+            Command::Build { site, .. } | Command::Serve { site, .. } => Some(site),
+            Command::New { .. } | Command::Theme { .. } => None,
+        }
+    }
+}
+
+/// Options shared by every subcommand that loads a site. Anything
+/// set here takes precedence over the config file.
+#[derive(Args, Debug)]
+pub struct SiteArgs {
+    /// Enable strict mode in the handlebars parser. This causes
+    /// missing or unknown values to produce hard errors instead of
+    /// empty strings.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub strict: Option<bool>,
+
+    /// Overrides the base URL specified in the config file.
+    #[arg(long)]
+    pub base_url: Option<String>,
+
+    /// Render pages marked as drafts in their front matter.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub drafts: Option<bool>,
+
+    /// Overrides the output folder path specified in the config file.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Overrides the content folder path specified in the config file.
+    #[arg(long)]
+    pub content: Option<PathBuf>,
+
+    /// Overrides the theme folder path specified in the config file.
+    #[arg(long)]
+    pub theme: Option<PathBuf>,
+
+    /// Overrides the layouts folder path specified in the config file.
+    #[arg(long)]
+    pub layouts: Option<PathBuf>,
+
+    /// Overrides the assets folder path specified in the config file.
+    #[arg(long)]
+    pub assets: Option<PathBuf>,
+
+    /// Overrides the data folder path specified in the config file.
+    #[arg(long)]
+    pub data: Option<PathBuf>,
+
+    /// Path to the config file.
+    #[arg(short, long, default_value = "./mllt.toml")]
+    pub config: PathBuf,
 }
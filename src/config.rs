@@ -16,13 +16,19 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use color_eyre::eyre::{Context as _, Result};
+use color_eyre::eyre::{bail, eyre, Context as _, Result};
 use hashbrown::HashMap;
-use serde::{Deserialize, Serialize};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::bundled::Bundling;
 use crate::cli::Cli;
+use crate::fingerprint::Fingerprint;
+use crate::images::ImageOptions;
+use crate::platforms;
+use crate::theme::{self, Theme};
+use crate::url::BaseUrl;
 
 /// A unified configuration struct, parsed from a `mllt.toml`
 /// site configuration file.
@@ -31,6 +37,14 @@ pub struct Config {
     /// Project-specific options.
     pub site: Site,
 
+    /// Links to show on the site, from `[[links]]` tables.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<Link>,
+
+    /// How images in the assets folder are processed, if at all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<ImageOptions>,
+
     /// Extra values stored in the config for convenience lookup
     pub params: HashMap<String, serde_json::Value>,
 }
@@ -45,19 +59,29 @@ impl Default for Config {
                 out_dir: "./output".into(),
                 content: "./content".into(),
                 theme: Some("./theme".into()),
+                layouts: Some("./layouts".into()),
                 assets: Some("./assets".into()),
+                data: Some("./data".into()),
+                favicon: None,
                 strict: false,
+                default_layout: Some("theme/page".into()),
+                drafts: false,
+                manifest: false,
+                fingerprint: Fingerprint::Off,
+                bundled: Bundling::Inline,
+                prune: false,
+                protect: vec!["CNAME".into(), ".well-known/".into()],
             },
+            links: vec![
+                Link::on("bluesky", "example.bsky.social"),
+                Link::new("My Blog", "https://blog.example.com"),
+                Link::on("github", "example"),
+            ],
+            images: None,
             params: {
                 let mut hm: HashMap<String, serde_json::Value> = HashMap::new();
                 hm.insert("title".into(), "MLLT Example Site".into());
                 hm.insert("desc".into(), "This is an example MLLT site.".into());
-                hm.insert("some_nonstring_value".into(), 42.into());
-                hm.insert("links".into(), serde_json::json!([
-                    { "name": "My Social Media", "value": "@example.bsky.app", "iconuri": "./bsky_icon.png" },
-                    { "name": "My Blog", "value": "https://blog.example.com" },
-                    { "name": "My Github", "value": "https://github.com", "iconuri": "./gh_icon.png" },
-                ]));
                 hm.insert(
                     "made_with".into(),
                     serde_json::json!({
@@ -88,15 +112,69 @@ pub struct Site {
     /// any partials in the simplest of outputs, so this is optional.
     pub theme: Option<PathBuf>,
 
+    /// Partials overriding the theme's ones of the same name, for
+    /// customizing a shared theme without copying all of it.
+    pub layouts: Option<PathBuf>,
+
     /// Static assets folder copied directly to output.
     /// No assets folder means no static assets will be copied.
     pub assets: Option<PathBuf>,
 
+    /// Structured data folder. Its `.toml`, `.json`, `.yaml` and `.csv`
+    /// files are made available to templates under `data`.
+    pub data: Option<PathBuf>,
+
+    /// Square image (PNG, JPEG or SVG) that the favicon, app icons
+    /// and `site.webmanifest` are generated from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<PathBuf>,
+
     /// Enable strict mode in the handlebars parser. This causes
     /// missing or unknown values to produce hard errors instead of
     /// empty strings.
     #[serde(default = "default_false")]
     pub strict: bool,
+
+    /// Theme template that markdown content pages are rendered
+    /// into, unless they name a layout of their own.
+    pub default_layout: Option<String>,
+
+    /// Render pages marked as drafts in their front matter.
+    #[serde(default = "default_false")]
+    pub drafts: bool,
+
+    /// Keep a manifest of content hashes in the output folder and
+    /// use it to decide which assets need copying, instead of
+    /// comparing file modification times.
+    #[serde(default = "default_false")]
+    pub manifest: bool,
+
+    /// Publish assets under names containing a hash of their contents,
+    /// so browsers never use a stale cached copy: `off`, `alongside`
+    /// (keep the original names too) or `replace`.
+    #[serde(default)]
+    pub fingerprint: Fingerprint,
+
+    /// Whether the `bundled` helper puts stylesheets
+    /// `inline` in a `<style>` tag, or `link`s to them.
+    #[serde(default)]
+    pub bundled: Bundling,
+
+    /// Delete files from the output folder which the
+    /// current build didn't produce.
+    #[serde(default = "default_false")]
+    pub prune: bool,
+
+    /// Globs, relative to the output folder, matching files which
+    /// are managed elsewhere and must never be pruned.
+    #[serde(default)]
+    pub protect: Vec<String>,
+}
+
+impl Site {
+    pub fn base_url(&self) -> BaseUrl {
+        BaseUrl::parse(&self.baseurl)
+    }
 }
 
 fn default_outdir() -> PathBuf {
@@ -107,11 +185,231 @@ const fn default_false() -> bool {
     false
 }
 
+const fn default_true() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_zero(value: &i64) -> bool {
+    *value == 0
+}
+
+/// A single entry in the site's `[[links]]` list. Fields are checked
+/// as they're read, so that `toml` can point errors at the offending value.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Link {
-    name: String,
-    value: String,
-    icon: Option<String>,
+    /// Text shown for the link. Defaults to the platform's name.
+    #[serde(
+        default,
+        deserialize_with = "link_name",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub name: Option<String>,
+
+    /// Where the link points to. Built from `handle` for platform links.
+    #[serde(
+        default,
+        deserialize_with = "link_url",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub url: Option<String>,
+
+    /// A [built-in platform](crate::platforms), which fills
+    /// in the link's name, URL and icon from its `handle`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+
+    /// The account on `platform`, e.g. `you` or `@you@mastodon.social`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+
+    /// URL of an image to show next to the link.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+
+    /// Inline `<svg>` markup to show next to the link,
+    /// used when there's no `icon`.
+    #[serde(
+        default,
+        deserialize_with = "link_icon_svg",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub icon_svg: Option<String>,
+
+    /// The anchor's `rel` attribute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rel: Option<String>,
+
+    /// The anchor's `target` attribute, e.g. `_blank`.
+    #[serde(
+        default,
+        deserialize_with = "link_target",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target: Option<String>,
+
+    /// Free-form label for grouping links into sections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+
+    /// Links are sorted by this, lowest first. Links with
+    /// the same order keep the order they were written in.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub order: i64,
+
+    /// Disabled links are left out of the site.
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub enabled: bool,
+}
+
+impl Link {
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            url: Some(url.into()),
+            ..Self::empty()
+        }
+    }
+
+    /// A link to a handle on a built-in platform.
+    pub fn on(platform: impl Into<String>, handle: impl Into<String>) -> Self {
+        Self {
+            platform: Some(platform.into()),
+            handle: Some(handle.into()),
+            ..Self::empty()
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            name: None,
+            url: None,
+            platform: None,
+            handle: None,
+            icon: None,
+            icon_svg: None,
+            rel: None,
+            target: None,
+            group: None,
+            order: 0,
+            enabled: true,
+        }
+    }
+
+    /// The link as templates see it: every field present, platform
+    /// links expanded, and links opening a new tab protected from
+    /// the opened page. `theme_icons` are checked in order for a
+    /// `<platform>.svg` overriding the built-in icon.
+    fn to_context(&self, theme_icons: &[PathBuf]) -> Result<serde_json::Value> {
+        let platform = match &self.platform {
+            Some(id) => Some(platforms::find(id).ok_or(eyre!(
+                "Unknown platform \"{id}\", expected one of {}",
+                platforms::ids()
+            ))?),
+            None => None,
+        };
+        if platform.is_none() && self.handle.is_some() {
+            bail!("A link's `handle` needs a `platform` to go with it");
+        }
+
+        let url = match (&self.url, platform, &self.handle) {
+            (Some(url), _, _) => url.clone(),
+            (None, Some(platform), Some(handle)) => platform.url(handle).map_err(|e| eyre!(e))?,
+            (None, Some(platform), None) => {
+                bail!("A {} link needs a `handle` or a `url`", platform.name)
+            }
+            (None, None, _) => bail!("A link needs a `url` or a `platform`"),
+        };
+
+        let name = match (&self.name, platform) {
+            (Some(name), _) => name.clone(),
+            (None, Some(platform)) => platform.name.to_owned(),
+            (None, None) => bail!("Link \"{url}\" needs a `name`"),
+        };
+
+        let icon_svg = match (&self.icon_svg, platform) {
+            (Some(svg), _) => Some(svg.clone()),
+            (None, Some(_)) if self.icon.is_some() => None,
+            (None, Some(platform)) => {
+                let theme_icon = theme_icons
+                    .iter()
+                    .map(|dir| dir.join(format!("{}.svg", platform.id)))
+                    .find(|path| path.is_file());
+                match theme_icon {
+                    Some(path) => Some(
+                        fs::read_to_string(&path)
+                            .context(format!("Error reading \"{}\"", path.display()))?,
+                    ),
+                    None => Some(platform.icon.to_owned()),
+                }
+            }
+            (None, None) => None,
+        };
+
+        // Platform links point at the site owner's own accounts.
+        let mut rel: Vec<&str> = match (&self.rel, platform) {
+            (Some(rel), _) => vec![rel],
+            (None, Some(_)) => vec!["me"],
+            (None, None) => vec![],
+        };
+        if self.rel.is_none() && self.target.as_deref() == Some("_blank") {
+            rel.push("noopener noreferrer");
+        }
+        let rel = (!rel.is_empty()).then(|| rel.join(" "));
+
+        Ok(serde_json::json!({
+            "name": name,
+            "url": url,
+            "platform": self.platform,
+            "handle": self.handle,
+            "icon": self.icon,
+            "icon_svg": icon_svg,
+            "rel": rel,
+            "target": self.target,
+            "group": self.group,
+            "order": self.order,
+        }))
+    }
+}
+
+fn link_name<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Option<String>, D::Error> {
+    let name = String::deserialize(d)?.trim().to_owned();
+    if name.is_empty() {
+        return Err(D::Error::custom("link `name` must not be empty"));
+    }
+    Ok(Some(name))
+}
+
+fn link_url<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Option<String>, D::Error> {
+    let url = String::deserialize(d)?.trim().to_owned();
+    crate::url::check(&url).map_err(|e| D::Error::custom(format!("link `url` {e}")))?;
+    Ok(Some(url))
+}
+
+fn link_icon_svg<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Option<String>, D::Error> {
+    let svg = String::deserialize(d)?;
+    if !svg.trim_start().starts_with("<svg") {
+        return Err(D::Error::custom(
+            "link `icon_svg` must be inline `<svg>` markup",
+        ));
+    }
+    Ok(Some(svg))
+}
+
+fn link_target<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Option<String>, D::Error> {
+    let target = String::deserialize(d)?;
+    let is_keyword = ["_blank", "_self", "_parent", "_top"].contains(&target.as_str());
+    if target.is_empty() || (target.starts_with('_') && !is_keyword) {
+        return Err(D::Error::custom(format!(
+            "invalid link `target` \"{target}\", expected `_blank`, \
+             `_self`, `_parent`, `_top` or a window name"
+        )));
+    }
+    Ok(Some(target))
 }
 
 impl Config {
@@ -124,41 +422,112 @@ impl Config {
 
     pub fn from_str(s: impl AsRef<str>) -> Result<Config> {
         let config: Config = toml::from_str(s.as_ref())?;
+        config.check_links(s.as_ref())?;
         Ok(config)
     }
 
+    /// Catch links whose fields are fine on their own but don't
+    /// add up, like a platform without a handle, at load time.
+    fn check_links(&self, source: &str) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Spans {
+            #[serde(default)]
+            links: Vec<toml::Spanned<toml::Value>>,
+        }
+
+        for (i, link) in self.links.iter().enumerate() {
+            if let Err(e) = link.to_context(&[]) {
+                let line = toml::from_str::<Spans>(source)
+                    .ok()
+                    .and_then(|spans| spans.links.get(i).map(|l| l.span().start))
+                    .map(|offset| source[..offset].matches('\n').count() + 1);
+                return Err(match line {
+                    Some(line) => e.wrap_err(format!("Invalid link at line {line}")),
+                    None => e.wrap_err(format!("Invalid link #{}", i + 1)),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enabled links in display order, ready for templates.
+    pub fn links(&self) -> Result<Vec<serde_json::Value>> {
+        let theme_icons: Vec<PathBuf> = self
+            .themes()?
+            .iter()
+            .map(|theme| theme.dir.join("icons"))
+            .collect();
+        let mut links: Vec<&Link> = self.links.iter().filter(|l| l.enabled).collect();
+        links.sort_by_key(|l| l.order);
+        links
+            .into_iter()
+            .map(|l| l.to_context(&theme_icons))
+            .collect()
+    }
+
+    /// The site's theme followed by the themes it inherits from.
+    pub fn themes(&self) -> Result<Vec<Theme>> {
+        match &self.site.theme {
+            Some(dir) => theme::chain(dir),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Every folder the site is built from, which the
+    /// output folder must be kept apart from.
+    pub fn source_dirs(&self) -> Result<Vec<PathBuf>> {
+        let mut dirs = vec![self.site.content.clone()];
+        dirs.extend(self.themes()?.into_iter().map(|t| t.dir));
+        dirs.extend(self.site.layouts.iter().cloned());
+        dirs.extend(self.site.assets.iter().cloned());
+        dirs.extend(self.site.data.iter().cloned());
+        Ok(dirs)
+    }
+
     pub fn update_from(&mut self, cli: &Cli) {
-        match &cli.command {
-            crate::cli::Command::Build {
-                strict,
-                output,
-                content,
-                theme,
-                assets,
-                config: _config,
-            } => {
-                if let Some(is_strict) = strict {
-                    self.site.strict = *is_strict;
-                }
+        let Some(args) = cli.command.site_args() else {
+            return;
+        };
 
-                if let Some(output_folder) = output.clone() {
-                    self.site.out_dir = output_folder;
-                }
+        if let Some(base_url) = args.base_url.clone() {
+            self.site.baseurl = base_url;
+        }
 
-                if let Some(content_folder) = content.clone() {
-                    self.site.content = content_folder;
-                }
+        if let Some(is_strict) = args.strict {
+            self.site.strict = is_strict;
+        }
 
-                if let Some(theme_folder) = theme.clone() {
-                    self.site.theme = Some(theme_folder);
-                }
+        if let Some(drafts) = args.drafts {
+            self.site.drafts = drafts;
+        }
 
-                if let Some(assets_folder) = assets.clone() {
-                    self.site.assets = Some(assets_folder);
-                }
-            }
-            crate::cli::Command::Serve { strict: Some(is_strict), .. } => self.site.strict = *is_strict,
-            _ => {}
+        if let Some(output_folder) = args.output.clone() {
+            self.site.out_dir = output_folder;
+        }
+
+        if let Some(content_folder) = args.content.clone() {
+            self.site.content = content_folder;
+        }
+
+        if let Some(theme_folder) = args.theme.clone() {
+            self.site.theme = Some(theme_folder);
+        }
+
+        if let Some(layouts_folder) = args.layouts.clone() {
+            self.site.layouts = Some(layouts_folder);
+        }
+
+        if let Some(assets_folder) = args.assets.clone() {
+            self.site.assets = Some(assets_folder);
+        }
+
+        if let Some(data_folder) = args.data.clone() {
+            self.site.data = Some(data_folder);
+        }
+
+        if let crate::cli::Command::Build { prune: Some(prune), .. } = &cli.command {
+            self.site.prune = *prune;
         }
     }
 
@@ -171,10 +540,75 @@ impl Config {
 impl TryFrom<&Config> for serde_json::Value {
     type Error = color_eyre::eyre::Error;
     fn try_from(value: &Config) -> std::result::Result<Self, Self::Error> {
+        let data = match &value.site.data {
+            Some(dir) => crate::data::load(dir)?,
+            None => serde_json::Value::Object(Default::default()),
+        };
+
         Ok(serde_json::json!({
             "site": value.site,
+            "links": value.links()?,
             "params": value.params,
-            "_bundled_normalize": include_str!("normalize.min.css"),
+            "data": data,
+            "_bundled": crate::bundled::context(),
+            // The name themes used before there was more than one.
+            "_bundled_normalize": crate::bundled::find("normalize").map(|s| s.css),
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SITE: &str = "[site]\nbaseURL = \"/\"\ncontent = \"content\"\n\n[params]\n";
+
+    fn load(links: &str) -> Result<Config> {
+        Config::from_str(format!("{SITE}\n{links}"))
+    }
+
+    #[test]
+    fn platform_links_are_expanded() {
+        let config = load("[[links]]\nplatform = \"mastodon\"\nhandle = \"@you@mastodon.social\"")
+            .unwrap();
+        let link = config.links[0].to_context(&[]).unwrap();
+        assert_eq!(link["name"], "Mastodon");
+        assert_eq!(link["url"], "https://mastodon.social/@you");
+        assert!(link["icon_svg"].as_str().unwrap().starts_with("<svg"));
+    }
+
+    #[test]
+    fn handle_without_platform() {
+        let e = load("[[links]]\nurl = \"https://example.com\"\nhandle = \"you\"").unwrap_err();
+        assert_eq!(e.to_string(), "Invalid link at line 7");
+        assert!(format!("{e:#}").contains("needs a `platform`"));
+    }
+
+    #[test]
+    fn inline_links_are_located() {
+        // Top-level keys have to come before the `[site]` table.
+        let e = Config::from_str(format!(
+            "links = [{{ name = \"a\", url = \"https://a.dev\" }}, {{ platform = \"github\" }}]\n{SITE}"
+        ))
+        .unwrap_err();
+        assert_eq!(e.to_string(), "Invalid link at line 1");
+
+        let e = Config::from_str(format!(
+            "links = [\n  {{ name = \"a\", url = \"https://a.dev\" }},\n  {{ name = \"b\", url = \"https://b.dev\" }}, {{ handle = \"you\" }},\n]\n{SITE}"
+        ))
+        .unwrap_err();
+        assert_eq!(e.to_string(), "Invalid link at line 3");
+    }
+
+    #[test]
+    fn unknown_platform() {
+        let e = load("[[links]]\nplatform = \"myspace\"\nhandle = \"you\"").unwrap_err();
+        assert!(format!("{e:#}").contains("Unknown platform \"myspace\""));
+    }
+
+    #[test]
+    fn invalid_handle() {
+        let e = load("[[links]]\nplatform = \"matrix\"\nhandle = \"you\"").unwrap_err();
+        assert!(format!("{e:#}").contains("Matrix handles need a server"));
+    }
+}
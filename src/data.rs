@@ -0,0 +1,255 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use color_eyre::eyre::{bail, eyre, Context as _, Result};
+use serde_json::{Map, Value};
+use std::path::Path;
+use tracing::debug;
+use walkdir::WalkDir;
+
+/// Load every data file under `dir` into a single object, nested by
+/// folder and keyed by file name. `data/links/social.toml` ends up at
+/// `links.social`. Files of unknown types are ignored, and a missing
+/// folder is the same as an empty one, since git doesn't keep those.
+pub fn load(dir: impl AsRef<Path>) -> Result<Value> {
+    let dir = dir.as_ref();
+    if !dir.exists() {
+        debug!("No data folder at \"{}\"", dir.display());
+        return Ok(Value::Object(Map::new()));
+    }
+    if !dir.is_dir() {
+        bail!(
+            "\"{}\" is not a folder, so it can't hold data files.",
+            dir.display()
+        );
+    }
+
+    let mut root = Map::new();
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path();
+        let Some(value) =
+            parse_file(path).context(format!("Error loading data file \"{}\"", path.display()))?
+        else {
+            debug!("Ignored non-data file: {}", path.display());
+            continue;
+        };
+
+        let relative_path = path.strip_prefix(dir)?.with_extension("");
+        let keys: Vec<&str> = relative_path
+            .iter()
+            .map(|part| part.to_str().ok_or(eyre!("Invalid data file path")))
+            .collect::<Result<_>>()?;
+
+        insert(&mut root, &keys, value)
+            .context(format!("Error loading data file \"{}\"", path.display()))?;
+        debug!("Loaded data file: {}", path.display());
+    }
+
+    Ok(Value::Object(root))
+}
+
+fn parse_file(path: &Path) -> Result<Option<Value>> {
+    let extension = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let value = match extension.to_ascii_lowercase().as_str() {
+        "toml" => serde_json::to_value(stringify_datetimes(toml::from_str(
+            &std::fs::read_to_string(path)?,
+        )?))?,
+        "json" => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+        "yaml" | "yml" => serde_yaml_ng::from_str(&std::fs::read_to_string(path)?)?,
+        "csv" => parse_csv(path)?,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(value))
+}
+
+/// CSV files become an array of objects, one per row,
+/// keyed by the header row.
+fn parse_csv(path: &Path) -> Result<Value> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers = reader.headers()?.clone();
+
+    let rows = reader
+        .records()
+        .map(|record| {
+            let record = record?;
+            Ok(Value::Object(
+                headers
+                    .iter()
+                    .zip(record.iter())
+                    .map(|(k, v)| (k.to_owned(), Value::String(v.to_owned())))
+                    .collect(),
+            ))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Value::Array(rows))
+}
+
+/// TOML dates and times would otherwise reach templates as objects
+/// that nothing can read, rather than strings the `date` helper takes.
+/// Used for TOML data files and front matter alike.
+pub fn stringify_datetimes(value: toml::Value) -> toml::Value {
+    match value {
+        toml::Value::Datetime(datetime) => toml::Value::String(datetime.to_string()),
+        toml::Value::Array(values) => {
+            toml::Value::Array(values.into_iter().map(stringify_datetimes).collect())
+        }
+        toml::Value::Table(table) => toml::Value::Table(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, stringify_datetimes(v)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Insert `value` at the nested path `keys`, merging it
+/// with any object that's already there.
+fn insert(map: &mut Map<String, Value>, keys: &[&str], value: Value) -> Result<()> {
+    let Some((key, rest)) = keys.split_first() else {
+        return Ok(());
+    };
+
+    if rest.is_empty() {
+        return match (map.get_mut(*key), value) {
+            (None, value) => {
+                map.insert((*key).to_owned(), value);
+                Ok(())
+            }
+            (Some(Value::Object(existing)), Value::Object(value)) => {
+                for (k, v) in value {
+                    insert(existing, &[k.as_str()], v)?;
+                }
+                Ok(())
+            }
+            (Some(_), _) => bail!("`{key}` is already defined by another data file."),
+        };
+    }
+
+    match map
+        .entry((*key).to_owned())
+        .or_insert_with(|| Value::Object(Map::new()))
+    {
+        Value::Object(child) => insert(child, rest, value),
+        _ => bail!("`{key}` is already defined by another data file."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn missing_folder_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = load(dir.path().join("data")).unwrap();
+        assert_eq!(data, json!({}));
+    }
+
+    #[test]
+    fn file_instead_of_folder_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("data");
+        std::fs::write(&file, "").unwrap();
+        assert!(load(file).is_err());
+    }
+
+    #[test]
+    fn files_are_nested_by_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("links")).unwrap();
+        std::fs::write(dir.path().join("links/social.toml"), "count = 2").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let data = load(dir.path()).unwrap();
+        assert_eq!(data, json!({ "links": { "social": { "count": 2 } } }));
+    }
+
+    /// The data loaded from a folder holding just `name`.
+    fn load_one(name: &str, contents: &str) -> Value {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(name), contents).unwrap();
+        load(dir.path()).unwrap()
+    }
+
+    #[test]
+    fn toml_datetimes_are_strings() {
+        let data = load_one(
+            "events.toml",
+            "start = 2024-01-02
+[[talks]]
+at = 2024-01-02T10:30:00Z",
+        );
+        assert_eq!(
+            data,
+            json!({ "events": { "start": "2024-01-02", "talks": [{ "at": "2024-01-02T10:30:00Z" }] } })
+        );
+    }
+
+    #[test]
+    fn json_and_yaml_files() {
+        let data = load_one("site.json", r#"{ "tags": ["a", "b"], "count": 2 }"#);
+        assert_eq!(data, json!({ "site": { "tags": ["a", "b"], "count": 2 } }));
+
+        let data = load_one("site.yaml", "tags:\n  - a\n  - b\ncount: 2\n");
+        assert_eq!(data, json!({ "site": { "tags": ["a", "b"], "count": 2 } }));
+        let data = load_one("site.YML", "count: 2\n");
+        assert_eq!(data, json!({ "site": { "count": 2 } }));
+    }
+
+    #[test]
+    fn csv_rows_are_keyed_by_header() {
+        let data = load_one("people.csv", "name,role\nAda,author\nGrace,editor\n");
+        assert_eq!(
+            data,
+            json!({ "people": [
+                { "name": "Ada", "role": "author" },
+                { "name": "Grace", "role": "editor" },
+            ] })
+        );
+    }
+
+    #[test]
+    fn file_and_folder_of_the_same_name_are_merged() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("links")).unwrap();
+        std::fs::write(dir.path().join("links.toml"), "title = \"Links\"").unwrap();
+        std::fs::write(dir.path().join("links/social.toml"), "count = 2").unwrap();
+
+        let data = load(dir.path()).unwrap();
+        assert_eq!(
+            data,
+            json!({ "links": { "title": "Links", "social": { "count": 2 } } })
+        );
+
+        // But not when both define the same key.
+        std::fs::write(dir.path().join("links.toml"), "social = 1").unwrap();
+        assert!(load(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use handlebars::{
+    template::{Parameter, Template, TemplateElement},
+    Path,
+};
+use std::collections::BTreeSet;
+
+/// Everything a single template reads, not counting
+/// the templates it pulls in itself.
+#[derive(Debug, Default, Clone)]
+pub struct TemplateDeps {
+    /// Names of partials included with `{{> name}}` and
+    /// layouts used with `{{#theme "name"}}`.
+    pub partials: BTreeSet<String>,
+
+    /// The top-level `params` keys the template reads.
+    pub params: ParamDeps,
+
+    /// Set when the template does something that can't be analysed
+    /// statically, like including a partial chosen at render time.
+    /// Pages depending on an opaque template are always re-rendered.
+    pub opaque: bool,
+
+    /// Set when the template reads the clock, like `{{date "now"}}`,
+    /// so its output changes without any of its inputs changing.
+    pub clock: bool,
+
+    /// Set when the template uses a helper that looks at the asset
+    /// files themselves, like `asset_url` checking that they exist.
+    pub assets: bool,
+
+    /// Names of the bundled stylesheets included with `{{bundled}}`.
+    pub bundled: BTreeSet<String>,
+
+    /// Set when `{{bundled}}` is passed a name only known at render
+    /// time, so that any of the stylesheets could be included.
+    pub any_bundled: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum ParamDeps {
+    Keys(BTreeSet<String>),
+    /// The template reads `params` as a whole, e.g. `{{#with params}}`.
+    All,
+}
+
+impl Default for ParamDeps {
+    fn default() -> Self {
+        ParamDeps::Keys(BTreeSet::new())
+    }
+}
+
+impl ParamDeps {
+    pub fn extend(&mut self, other: &ParamDeps) {
+        match (&mut *self, other) {
+            (ParamDeps::All, _) => {}
+            (_, ParamDeps::All) => *self = ParamDeps::All,
+            (ParamDeps::Keys(keys), ParamDeps::Keys(other)) => keys.extend(other.iter().cloned()),
+        }
+    }
+
+    fn insert(&mut self, key: Option<String>) {
+        match (&mut *self, key) {
+            (ParamDeps::All, _) => {}
+            (ParamDeps::Keys(keys), Some(key)) => {
+                keys.insert(key);
+            }
+            (ParamDeps::Keys(_), None) => *self = ParamDeps::All,
+        }
+    }
+}
+
+impl TemplateDeps {
+    pub fn of(template: &Template) -> Self {
+        let mut deps = Self::default();
+        deps.visit_template(template);
+        deps
+    }
+
+    fn visit_template(&mut self, template: &Template) {
+        for element in &template.elements {
+            self.visit_element(element);
+        }
+    }
+
+    fn visit_element(&mut self, element: &TemplateElement) {
+        match element {
+            TemplateElement::Expression(ht)
+            | TemplateElement::HtmlExpression(ht)
+            | TemplateElement::HelperBlock(ht) => {
+                match ht.name.as_name() {
+                    Some("theme") => match ht.params.first() {
+                        Some(Parameter::Literal(serde_json::Value::String(layout))) => {
+                            self.partials.insert(layout.clone());
+                        }
+                        _ => self.opaque = true,
+                    },
+                    // A date from a variable could still turn out to be "now".
+                    Some("date") => match ht.params.first() {
+                        Some(Parameter::Literal(date)) if date != "now" => {}
+                        _ => self.clock = true,
+                    },
+                    Some("asset_url" | "asset" | "asset_integrity" | "image") => {
+                        self.assets = true
+                    }
+                    Some("bundled") => {
+                        for param in &ht.params {
+                            match param {
+                                Parameter::Literal(serde_json::Value::String(name)) => {
+                                    self.bundled.insert(name.clone());
+                                }
+                                _ => self.any_bundled = true,
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+
+                self.visit_param(&ht.name);
+                ht.params.iter().for_each(|p| self.visit_param(p));
+                ht.hash.values().for_each(|p| self.visit_param(p));
+                if let Some(t) = &ht.template {
+                    self.visit_template(t);
+                }
+                if let Some(t) = &ht.inverse {
+                    self.visit_template(t);
+                }
+            }
+            TemplateElement::PartialExpression(dt) | TemplateElement::PartialBlock(dt) => {
+                match &dt.name {
+                    Parameter::Name(name) => {
+                        self.partials.insert(name.clone());
+                    }
+                    Parameter::Literal(serde_json::Value::String(name)) => {
+                        self.partials.insert(name.clone());
+                    }
+                    _ => self.opaque = true,
+                }
+
+                dt.params.iter().for_each(|p| self.visit_param(p));
+                dt.hash.values().for_each(|p| self.visit_param(p));
+                if let Some(t) = &dt.template {
+                    self.visit_template(t);
+                }
+            }
+            TemplateElement::DecoratorExpression(dt) | TemplateElement::DecoratorBlock(dt) => {
+                dt.params.iter().for_each(|p| self.visit_param(p));
+                dt.hash.values().for_each(|p| self.visit_param(p));
+                if let Some(t) = &dt.template {
+                    self.visit_template(t);
+                }
+            }
+            TemplateElement::RawString(_) | TemplateElement::Comment(_) => {}
+            _ => self.opaque = true,
+        }
+    }
+
+    fn visit_param(&mut self, param: &Parameter) {
+        match param {
+            Parameter::Name(name) => self.visit_path(name),
+            Parameter::Path(Path::Relative((_, raw))) => self.visit_path(raw),
+            Parameter::Subexpression(sub) => self.visit_element(&sub.element),
+            Parameter::Path(Path::Local((_, name, _))) if name == "root" => {
+                self.params.insert(None)
+            }
+            Parameter::Path(Path::Local(_)) | Parameter::Literal(_) => {}
+            _ => self.opaque = true,
+        }
+    }
+
+    fn visit_path(&mut self, raw: &str) {
+        let mut rest = raw;
+        while let Some(stripped) = ["../", "./", "this.", "this/", "@root.", "@root/"]
+            .iter()
+            .find_map(|prefix| rest.strip_prefix(prefix))
+        {
+            rest = stripped;
+        }
+
+        // The whole context, `params` included, e.g. `{{json @root}}`.
+        if ["", ".", "..", "this", "@root"].contains(&rest) {
+            self.params.insert(None);
+            return;
+        }
+
+        let mut segments = rest.split(['.', '/']);
+        if segments.next() == Some("params") {
+            let key = segments
+                .next()
+                .map(|key| key.trim_start_matches('[').trim_end_matches(']').to_owned());
+            self.params.insert(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deps(source: &str) -> TemplateDeps {
+        TemplateDeps::of(&Template::compile(source).unwrap())
+    }
+
+    fn keys(deps: &TemplateDeps) -> Vec<&str> {
+        match &deps.params {
+            ParamDeps::Keys(keys) => keys.iter().map(String::as_str).collect(),
+            ParamDeps::All => panic!("expected individual params keys"),
+        }
+    }
+
+    #[test]
+    fn partials_and_layouts() {
+        let deps = deps(
+            r#"{{#theme "theme/page"}}{{> theme/header}}{{#> theme/card}}x{{/theme/card}}{{/theme}}"#,
+        );
+        assert_eq!(
+            deps.partials.iter().collect::<Vec<_>>(),
+            ["theme/card", "theme/header", "theme/page"]
+        );
+        assert!(!deps.opaque);
+    }
+
+    #[test]
+    fn params_keys_through_blocks_and_parents() {
+        let deps = deps(
+            "{{params.title}}{{#each links}}{{../params.accent}}{{/each}}\
+             {{#if (eq @root.params.mode \"dark\")}}{{upper params.[made_with].name}}{{/if}}",
+        );
+        assert_eq!(keys(&deps), ["accent", "made_with", "mode", "title"]);
+    }
+
+    #[test]
+    fn params_as_a_whole() {
+        assert!(matches!(deps("{{#with params}}{{title}}{{/with}}").params, ParamDeps::All));
+        assert!(matches!(deps("{{json params}}").params, ParamDeps::All));
+        for source in [
+            "{{json this}}",
+            "{{json @root}}",
+            "{{#with @root}}{{json params.title}}{{/with}}",
+            "{{#each this}}{{@key}}{{/each}}",
+            "{{#each links}}{{json ../this}}{{/each}}",
+        ] {
+            assert!(matches!(deps(source).params, ParamDeps::All), "{source}");
+        }
+        assert!(!matches!(deps("{{json @root.site}}").params, ParamDeps::All));
+    }
+
+    #[test]
+    fn dynamic_partials_are_opaque() {
+        assert!(deps("{{> (lookup page \"layout\")}}").opaque);
+        assert!(deps("{{#theme page.layout}}x{{/theme}}").opaque);
+    }
+
+    #[test]
+    fn the_clock() {
+        assert!(deps("{{date \"now\" \"%Y\"}}").clock);
+        assert!(deps("{{date page.params.date}}").clock);
+        assert!(deps("{{#if (date \"now\")}}x{{/if}}").clock);
+        assert!(!deps("{{date \"2024-01-01\"}}").clock);
+        assert!(!deps("{{date 1700000000}}").clock);
+    }
+
+    #[test]
+    fn asset_helpers() {
+        assert!(deps("{{asset_url \"style.css\"}}").assets);
+        assert!(deps("{{{image \"photo.jpg\" alt=\"\"}}}").assets);
+        assert!(!deps("{{relURL \"style.css\"}}").assets);
+    }
+
+    #[test]
+    fn bundled_stylesheets() {
+        let both = deps("{{{bundled \"normalize\" \"reset\"}}}{{{bundled \"reset\"}}}");
+        assert_eq!(both.bundled.iter().collect::<Vec<_>>(), ["normalize", "reset"]);
+        assert!(!both.any_bundled);
+        assert!(deps("{{{bundled \"reset\" params.css}}}").any_bundled);
+        assert!(deps("{{_bundled.normalize}}").bundled.is_empty());
+    }
+
+    #[test]
+    fn extend_params() {
+        let mut params = deps("{{params.a}}").params;
+        params.extend(&deps("{{params.b}}").params);
+        assert!(matches!(&params, ParamDeps::Keys(keys) if keys.len() == 2));
+        params.extend(&ParamDeps::All);
+        assert!(matches!(params, ParamDeps::All));
+    }
+}
@@ -0,0 +1,291 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! The favicon and app icon set, generated from the one image
+//! set as `[site] favicon`.
+
+use color_eyre::eyre::{eyre, Context as _, Result};
+use hashbrown::HashSet;
+use image::{
+    codecs::ico::{IcoEncoder, IcoFrame},
+    imageops::{self, FilterType},
+    DynamicImage, ExtendedColorType, ImageReader, RgbaImage,
+};
+use resvg::{tiny_skia, usvg};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use crate::{manifest::FileRecord, url::BaseUrl};
+
+/// PNG icons made from the source image, and their sizes.
+const PNGS: &[(&str, u32)] = &[
+    ("favicon-16x16.png", 16),
+    ("favicon-32x32.png", 32),
+    ("apple-touch-icon.png", 180),
+    ("android-chrome-192x192.png", 192),
+    ("android-chrome-512x512.png", 512),
+];
+
+/// Sizes bundled into `favicon.ico`, for browsers that ask for it.
+const ICO_SIZES: &[u32] = &[16, 32, 48];
+
+const ICO: &str = "favicon.ico";
+const SVG: &str = "favicon.svg";
+const MANIFEST: &str = "site.webmanifest";
+
+fn is_svg(source: &Path) -> bool {
+    source
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+}
+
+/// Write the icon set and `site.webmanifest` into `dst`, returning
+/// the paths of the files written, relative to it.
+///
+/// `drawn` is the hash of the source the icons in `dst` were last
+/// drawn from, if known. They're only redrawn when that changes or
+/// some are missing, and `drawn` is updated to match.
+pub fn generate(
+    source: &Path,
+    dst: &Path,
+    name: Option<&str>,
+    base: &BaseUrl,
+    drawn: &mut Option<String>,
+) -> Result<HashSet<PathBuf>> {
+    let mut outputs: Vec<&str> = PNGS.iter().map(|(file, _)| *file).collect();
+    outputs.push(ICO);
+    if is_svg(source) {
+        outputs.push(SVG);
+    }
+
+    // The file type decides how it's drawn, so that's part of the hash.
+    let hash = format!(
+        "{}.{}",
+        FileRecord::of(source)?.hash,
+        source.extension().unwrap_or_default().to_string_lossy()
+    );
+    if drawn.as_ref() == Some(&hash) && outputs.iter().all(|file| dst.join(file).is_file()) {
+        debug!("Favicons are up to date.");
+    } else {
+        info!("Generating favicons...");
+        *drawn = None;
+        draw(source, dst).context(format!(
+            "Error generating favicons from \"{}\"",
+            source.display()
+        ))?;
+        *drawn = Some(hash);
+    }
+
+    let manifest = serde_json::json!({
+        "name": name.unwrap_or_default(),
+        "short_name": name.unwrap_or_default(),
+        "icons": PNGS
+            .iter()
+            .filter(|(file, _)| file.starts_with("android-chrome"))
+            .map(|(file, size)| serde_json::json!({
+                "src": base.rel(file),
+                "sizes": format!("{size}x{size}"),
+                "type": "image/png",
+            }))
+            .collect::<Vec<_>>(),
+        "start_url": base.rel(""),
+        "display": "standalone",
+    });
+    let manifest = serde_json::to_string_pretty(&manifest)?;
+    let manifest_path = dst.join(MANIFEST);
+    if std::fs::read_to_string(&manifest_path).ok().as_deref() != Some(manifest.as_str()) {
+        std::fs::write(&manifest_path, manifest)?;
+        debug!("Wrote: {}", manifest_path.display());
+    }
+    outputs.push(MANIFEST);
+
+    Ok(outputs.into_iter().map(PathBuf::from).collect())
+}
+
+fn draw(source: &Path, dst: &Path) -> Result<()> {
+    std::fs::create_dir_all(dst)?;
+    let render: Box<dyn Fn(u32) -> Result<RgbaImage>> = if is_svg(source) {
+        std::fs::copy(source, dst.join(SVG))?;
+        let tree = usvg::Tree::from_data(&std::fs::read(source)?, &usvg::Options::default())?;
+        Box::new(move |size| rasterize(&tree, size))
+    } else {
+        let image = ImageReader::open(source)?.with_guessed_format()?.decode()?;
+        Box::new(move |size| Ok(square(&image, size)))
+    };
+
+    for (file, size) in PNGS {
+        let path = dst.join(file);
+        render(*size)?.save(&path)?;
+        debug!("Wrote: {}", path.display());
+    }
+
+    let frames = ICO_SIZES
+        .iter()
+        .map(|&size| {
+            let icon = render(size)?;
+            Ok(IcoFrame::as_png(
+                icon.as_raw(),
+                size,
+                size,
+                ExtendedColorType::Rgba8,
+            )?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let path = dst.join(ICO);
+    IcoEncoder::new(std::fs::File::create(&path)?).encode_images(&frames)?;
+    debug!("Wrote: {}", path.display());
+
+    Ok(())
+}
+
+/// `image` scaled to fit a `size`-pixel square, centered
+/// on a transparent background if it isn't square itself.
+fn square(image: &DynamicImage, size: u32) -> RgbaImage {
+    let scaled = image.resize(size, size, FilterType::Lanczos3).to_rgba8();
+    let mut icon = RgbaImage::new(size, size);
+    let x = (size - scaled.width()) / 2;
+    let y = (size - scaled.height()) / 2;
+    imageops::overlay(&mut icon, &scaled, x.into(), y.into());
+    icon
+}
+
+/// Draw an SVG into a `size`-pixel square, keeping its aspect ratio.
+fn rasterize(tree: &usvg::Tree, size: u32) -> Result<RgbaImage> {
+    let mut pixmap = tiny_skia::Pixmap::new(size, size).ok_or(eyre!("Invalid icon size"))?;
+    let svg = tree.size();
+    let scale = size as f32 / svg.width().max(svg.height());
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        (size as f32 - svg.width() * scale) / 2.0,
+        (size as f32 - svg.height() * scale) / 2.0,
+    );
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia stores premultiplied alpha, `image` doesn't.
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(size, size, pixels).ok_or(eyre!("Invalid icon size"))
+}
+
+/// `<link>` tags for the icon set, for the `favicons` helper.
+pub fn links(source: &Path, base: &BaseUrl) -> String {
+    let mut links = vec![format!(
+        r#"<link rel="icon" href="{}" sizes="48x48">"#,
+        base.rel(ICO)
+    )];
+    if is_svg(source) {
+        links.push(format!(
+            r#"<link rel="icon" href="{}" type="image/svg+xml">"#,
+            base.rel(SVG)
+        ));
+    }
+    for size in [32, 16] {
+        links.push(format!(
+            r#"<link rel="icon" href="{}" type="image/png" sizes="{size}x{size}">"#,
+            base.rel(&format!("favicon-{size}x{size}.png"))
+        ));
+    }
+    links.push(format!(
+        r#"<link rel="apple-touch-icon" href="{}" sizes="180x180">"#,
+        base.rel("apple-touch-icon.png")
+    ));
+    links.push(format!(
+        r#"<link rel="manifest" href="{}">"#,
+        base.rel(MANIFEST)
+    ));
+    links.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_into(source: &Path, dst: &Path, drawn: &mut Option<String>) {
+        let base = BaseUrl::parse("example.com");
+        generate(source, dst, Some("Test"), &base, drawn).unwrap();
+    }
+
+    #[test]
+    fn redraws_only_when_needed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (source, dst) = (dir.path().join("logo.png"), dir.path().join("output"));
+        RgbaImage::from_pixel(64, 64, image::Rgba([255, 0, 0, 255]))
+            .save(&source)
+            .unwrap();
+
+        let mut drawn = None;
+        generate_into(&source, &dst, &mut drawn);
+        assert!(drawn.is_some());
+        assert!(dst.join(ICO).is_file());
+        assert!(!dst.join(SVG).exists());
+
+        // A marker survives as long as nothing is redrawn.
+        let marker = dst.join("favicon-16x16.png");
+        std::fs::write(&marker, "marker").unwrap();
+        generate_into(&source, &dst, &mut drawn);
+        assert_eq!(std::fs::read(&marker).unwrap(), b"marker");
+
+        std::fs::remove_file(dst.join("apple-touch-icon.png")).unwrap();
+        generate_into(&source, &dst, &mut drawn);
+        assert_ne!(std::fs::read(&marker).unwrap(), b"marker");
+        assert!(dst.join("apple-touch-icon.png").is_file());
+
+        std::fs::write(&marker, "marker").unwrap();
+        RgbaImage::from_pixel(64, 64, image::Rgba([0, 0, 255, 255]))
+            .save(&source)
+            .unwrap();
+        generate_into(&source, &dst, &mut drawn);
+        assert_ne!(std::fs::read(&marker).unwrap(), b"marker");
+    }
+
+    #[test]
+    fn webmanifest_names_the_site() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("logo.svg");
+        std::fs::write(
+            &source,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><rect width="10" height="10"/></svg>"#,
+        )
+        .unwrap();
+
+        let outputs = generate(
+            &source,
+            dir.path(),
+            Some("Test"),
+            &BaseUrl::parse("https://example.com/sub/"),
+            &mut None,
+        )
+        .unwrap();
+        assert!(outputs.contains(Path::new(SVG)));
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join(MANIFEST)).unwrap())
+                .unwrap();
+        assert_eq!(manifest["name"], "Test");
+        assert_eq!(manifest["start_url"], "/sub/");
+        assert_eq!(
+            manifest["icons"][0]["src"],
+            "/sub/android-chrome-192x192.png"
+        );
+    }
+}
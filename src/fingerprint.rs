@@ -0,0 +1,157 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use color_eyre::eyre::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha384};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::manifest::{manifest_key, FileRecord};
+
+/// How static assets are fingerprinted for cache busting.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Fingerprint {
+    /// Assets are copied under their own names.
+    #[default]
+    Off,
+    /// `style.css` is copied as both `style.css` and `style.<hash>.css`.
+    Alongside,
+    /// `style.css` is only copied as `style.<hash>.css`.
+    Replace,
+}
+
+/// Number of hex digits of the content hash put into file names.
+const HASH_LEN: usize = 10;
+
+/// `css/style.css` with content hash `hash` becomes `css/style.<hash>.css`.
+pub fn fingerprinted(relative_path: &Path, hash: &str) -> PathBuf {
+    let hash = &hash[..HASH_LEN.min(hash.len())];
+    let stem = relative_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let name = match relative_path.extension() {
+        Some(ext) => format!("{stem}.{hash}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{hash}"),
+    };
+    relative_path.with_file_name(name)
+}
+
+/// Where an asset ends up in the output folder, relative to it.
+pub fn outputs(relative_path: &Path, hash: &str, mode: Fingerprint) -> Vec<PathBuf> {
+    match mode {
+        Fingerprint::Off => vec![relative_path.to_owned()],
+        Fingerprint::Alongside => {
+            vec![relative_path.to_owned(), fingerprinted(relative_path, hash)]
+        }
+        Fingerprint::Replace => vec![fingerprinted(relative_path, hash)],
+    }
+}
+
+/// Hash of every asset's name and contents, which decides the
+/// fingerprinted URLs that pages link to.
+pub fn hash_all(dir: impl AsRef<Path>) -> Result<String> {
+    let dir = dir.as_ref();
+    let mut hasher = blake3::Hasher::new();
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let record = FileRecord::of(entry.path())?;
+        hasher.update(manifest_key(entry.path().strip_prefix(dir)?).as_bytes());
+        hasher.update(b"\0");
+        hasher.update(record.hash.as_bytes());
+        hasher.update(b"\0");
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// A Subresource Integrity hash of the file at `path`,
+/// for `<link>` and `<script>` tags' `integrity` attribute.
+pub fn integrity(path: impl AsRef<Path>) -> Result<String> {
+    let path = path.as_ref();
+    let contents = std::fs::read(path).context(format!("Error reading \"{}\"", path.display()))?;
+    Ok(format!(
+        "sha384-{}",
+        STANDARD.encode(Sha384::digest(&contents))
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "0123456789abcdef";
+
+    #[test]
+    fn fingerprinted_names() {
+        let name = |p: &str, hash: &str| fingerprinted(Path::new(p), hash);
+        assert_eq!(name("style.css", HASH), Path::new("style.0123456789.css"));
+        assert_eq!(
+            name("css/site.min.css", HASH),
+            Path::new("css/site.min.0123456789.css")
+        );
+        assert_eq!(name("LICENSE", HASH), Path::new("LICENSE.0123456789"));
+        assert_eq!(name("a.js", "abc"), Path::new("a.abc.js"));
+    }
+
+    #[test]
+    fn outputs_per_mode() {
+        let path = Path::new("js/app.js");
+        assert_eq!(outputs(path, HASH, Fingerprint::Off), [path]);
+        assert_eq!(
+            outputs(path, HASH, Fingerprint::Alongside),
+            [path, Path::new("js/app.0123456789.js")]
+        );
+        assert_eq!(
+            outputs(path, HASH, Fingerprint::Replace),
+            [Path::new("js/app.0123456789.js")]
+        );
+    }
+
+    #[test]
+    fn integrity_is_base64_sha384() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "abc").unwrap();
+        assert_eq!(
+            integrity(&path).unwrap(),
+            "sha384-ywB1P0WjXou1oD1pmsZQBycsMqsO3tFjGotgWkP/W+2AhgcroefMI1i67KE0yCWn"
+        );
+        assert!(integrity(dir.path().join("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn hash_all_covers_names_and_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.css"), "a").unwrap();
+        let before = hash_all(dir.path()).unwrap();
+
+        std::fs::write(dir.path().join("a.css"), "b").unwrap();
+        let changed = hash_all(dir.path()).unwrap();
+        assert_ne!(changed, before);
+
+        std::fs::rename(dir.path().join("a.css"), dir.path().join("b.css")).unwrap();
+        assert_ne!(hash_all(dir.path()).unwrap(), changed);
+    }
+}
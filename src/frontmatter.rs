@@ -0,0 +1,217 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use color_eyre::eyre::{bail, Context as _, Result};
+use serde::Deserialize;
+use std::path::{Component, PathBuf};
+
+use crate::data;
+
+/// Per-page settings and values from a block of TOML (fenced
+/// by `+++`) or YAML (fenced by `---`) at the top of a page.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct FrontMatter {
+    /// Where to write the page, relative to the output folder.
+    pub output: Option<PathBuf>,
+
+    /// Theme template to render the page into.
+    pub layout: Option<String>,
+
+    /// Drafts are only rendered when drafts are enabled.
+    #[serde(default)]
+    pub draft: bool,
+
+    /// Everything else, made available to the page as `page.params`.
+    #[serde(flatten)]
+    pub params: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A page's source, split into its front matter and body.
+pub struct Split<'a> {
+    pub front_matter: FrontMatter,
+    pub body: &'a str,
+    /// Number of lines taken up by the front matter, so that
+    /// errors in the body can report their real line numbers.
+    pub lines: usize,
+}
+
+/// Split the front matter off the top of `source`, if it has any.
+pub fn split(source: &str) -> Result<Split<'_>> {
+    let fence = match source.lines().next().map(str::trim_end) {
+        Some(fence @ ("+++" | "---")) => fence,
+        _ => {
+            return Ok(Split {
+                front_matter: FrontMatter::default(),
+                body: source,
+                lines: 0,
+            })
+        }
+    };
+
+    // Find the closing fence, keeping track of byte offsets.
+    let mut source_lines = source.split_inclusive('\n');
+    let block_start = source_lines.next().map_or(0, str::len);
+    let mut offset = block_start;
+    let mut lines = 1;
+    let mut closing = None;
+    for line in source_lines {
+        lines += 1;
+        if line.trim_end() == fence {
+            closing = Some(line.len());
+            break;
+        }
+        offset += line.len();
+    }
+
+    let Some(closing_len) = closing else {
+        bail!("Front matter opened with `{fence}` is never closed");
+    };
+    let block = &source[block_start..offset];
+    let body_start = offset + closing_len;
+
+    let mut front_matter: FrontMatter = if block.trim().is_empty() {
+        FrontMatter::default()
+    } else if fence == "+++" {
+        let value: toml::Value = toml::from_str(block).context("Invalid TOML front matter")?;
+        data::stringify_datetimes(value)
+            .try_into()
+            .context("Invalid TOML front matter")?
+    } else {
+        serde_yaml_ng::from_str(block).context("Invalid YAML front matter")?
+    };
+
+    if let Some(output) = &mut front_matter.output {
+        let escapes = output
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+        // `.` alone would name the output folder itself.
+        let file: PathBuf = output
+            .components()
+            .filter(|c| *c != Component::CurDir)
+            .collect();
+        if escapes || file.file_name().is_none() {
+            bail!(
+                "Front matter `output` must be a relative path inside the output folder, got \"{}\"",
+                output.display()
+            );
+        }
+        // Stored as the path the output folder's walk will find it
+        // under, so pruning doesn't mistake `./about.html` for stale.
+        *output = file;
+    }
+
+    Ok(Split {
+        front_matter,
+        body: &source[body_start..],
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn no_front_matter() {
+        let split = split("<h1>Hi</h1>\n").unwrap();
+        assert_eq!(split.body, "<h1>Hi</h1>\n");
+        assert_eq!(split.lines, 0);
+        assert!(split.front_matter.params.is_empty());
+    }
+
+    #[test]
+    fn toml_fence() {
+        let split = split("+++\nlayout = \"theme/post\"\ntitle = \"Hi\"\n+++\nbody\n").unwrap();
+        assert_eq!(split.front_matter.layout.as_deref(), Some("theme/post"));
+        assert_eq!(split.front_matter.params["title"], json!("Hi"));
+        assert_eq!(split.body, "body\n");
+        assert_eq!(split.lines, 4);
+    }
+
+    #[test]
+    fn yaml_fence() {
+        let split = split("---\ndraft: true\ntags: [a, b]\n---\nbody").unwrap();
+        assert!(split.front_matter.draft);
+        assert_eq!(split.front_matter.params["tags"], json!(["a", "b"]));
+        assert_eq!(split.body, "body");
+    }
+
+    #[test]
+    fn empty_block() {
+        let split = split("+++\n+++\nbody").unwrap();
+        assert_eq!(split.body, "body");
+        assert_eq!(split.lines, 2);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let split = split("+++\r\ntitle = \"Hi\"\r\n+++\r\nbody\r\n").unwrap();
+        assert_eq!(split.front_matter.params["title"], json!("Hi"));
+        assert_eq!(split.body, "body\r\n");
+        assert_eq!(split.lines, 3);
+    }
+
+    #[test]
+    fn fences_must_match_and_close() {
+        assert!(split("+++\ntitle = \"Hi\"\n---\nbody").is_err());
+        assert!(split("---\ntitle: Hi\n").is_err());
+    }
+
+    #[test]
+    fn fence_must_be_the_first_line() {
+        let source = "\n+++\ntitle = \"Hi\"\n+++\n";
+        assert_eq!(split(source).unwrap().body, source);
+    }
+
+    #[test]
+    fn toml_datetimes_are_strings() {
+        let split = split(
+            "+++\ndate = 2024-05-01\nupdated = 2024-05-02T10:30:00Z\n[[events]]\nat = 12:00:00\n+++\n",
+        )
+        .unwrap();
+        let params = &split.front_matter.params;
+        assert_eq!(params["date"], json!("2024-05-01"));
+        assert_eq!(params["updated"], json!("2024-05-02T10:30:00Z"));
+        assert_eq!(params["events"], json!([{ "at": "12:00:00" }]));
+    }
+
+    #[test]
+    fn output_path() {
+        let output = |path: &str| {
+            split(&format!("+++\noutput = \"{path}\"\n+++\n")).map(|s| s.front_matter.output)
+        };
+
+        assert_eq!(
+            output("feeds/atom.xml").unwrap(),
+            Some(PathBuf::from("feeds/atom.xml"))
+        );
+        assert_eq!(output("./404.html").unwrap(), Some(PathBuf::from("404.html")));
+        assert_eq!(
+            output("feeds/./atom.xml").unwrap(),
+            Some(PathBuf::from("feeds/atom.xml"))
+        );
+
+        assert!(output("../outside.html").is_err());
+        assert!(output("feeds/../../outside.html").is_err());
+        assert!(output("/etc/passwd").is_err());
+        assert!(output("").is_err());
+        assert!(output(".").is_err());
+        assert!(output("./").is_err());
+    }
+}
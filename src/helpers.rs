@@ -0,0 +1,982 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! The standard helper library available to every template. `eq`, `ne`,
+//! `gt`, `lt`, `and`, `or`, `not` and `len` are built into handlebars,
+//! as are the case helpers like `snakeCase` and `titleCase`.
+
+use chrono::{format::StrftimeItems, DateTime, NaiveDate, NaiveDateTime, Utc};
+use color_eyre::eyre::{bail, eyre, Context as _, Result};
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, RenderErrorReason,
+    ScopedJson,
+};
+use hashbrown::HashMap;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rhai::{module_resolvers::DummyModuleResolver, Engine};
+use serde_json::{Map, Number, Value};
+use std::{
+    cmp::Ordering,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tracing::{debug, info};
+use walkdir::WalkDir;
+
+use crate::{
+    bundled,
+    config::Config,
+    favicons,
+    fingerprint::{self, Fingerprint},
+    images::{self, ImageOptions},
+    manifest::{manifest_key, FileRecord},
+    url::BaseUrl,
+};
+
+type HelperFn = fn(&Args) -> Result<Value, String>;
+type BoxedHelperFn = Box<dyn Fn(&Args) -> Result<Value, String> + Send + Sync>;
+
+/// Helpers built into handlebars itself, including the
+/// `string_helpers` case conversions, and mllt's `theme`.
+const CORE: &[&str] = &[
+    "if", "unless", "each", "with", "lookup", "raw", "log", "eq", "ne", "gt", "gte", "lt", "lte",
+    "and", "or", "not", "len", "lowerCamelCase", "upperCamelCase", "snakeCase", "kebabCase",
+    "shoutySnakeCase", "shoutyKebabCase", "titleCase", "trainCase", "theme",
+];
+
+const LIBRARY: &[(&str, HelperFn)] = &[
+    ("upper", |a| Ok(a.str(0)?.to_uppercase().into())),
+    ("lower", |a| Ok(a.str(0)?.to_lowercase().into())),
+    ("capitalize", capitalize),
+    ("truncate", truncate),
+    ("join", join),
+    ("sort_by", sort_by),
+    ("group_by", group_by),
+    ("where", filter_where),
+    ("slugify", |a| Ok(slugify(a.str(0)?).into())),
+    ("json", json),
+    ("url_encode", url_encode),
+    ("date", date),
+    ("add", |a| math(a, |x, y| x + y)),
+    ("sub", |a| math(a, |x, y| x - y)),
+    ("mul", |a| math(a, |x, y| x * y)),
+    ("div", |a| math(a, |x, y| x / y)),
+    ("mod", |a| math(a, |x, y| x % y)),
+    ("min", |a| math(a, f64::min)),
+    ("max", |a| math(a, f64::max)),
+    ("round", |a| Ok(number(a.num(0)?.round()))),
+    ("floor", |a| Ok(number(a.num(0)?.floor()))),
+    ("ceil", |a| Ok(number(a.num(0)?.ceil()))),
+];
+
+/// Helpers registered separately from [`LIBRARY`], since
+/// they need more than their arguments to do their job.
+const SPECIAL: &[&str] = &[
+    "default",
+    "relURL",
+    "absURL",
+    "asset_url",
+    "asset",
+    "asset_integrity",
+    "image",
+    "favicons",
+    "bundled",
+];
+
+pub fn register(handlebars: &mut Handlebars, config: &Config) {
+    let site = &config.site;
+    for (name, f) in LIBRARY {
+        handlebars.register_helper(name, Box::new(ValueHelper::new(name, *f)));
+    }
+
+    // `default` exists to handle missing values,
+    // so it can't refuse them in strict mode.
+    handlebars.register_helper(
+        "default",
+        Box::new(ValueHelper {
+            lenient: true,
+            ..ValueHelper::new("default", default)
+        }),
+    );
+
+    let base = site.base_url();
+    handlebars.register_helper(
+        "relURL",
+        Box::new(ValueHelper::new("relURL", move |a| {
+            Ok(base.rel(&to_text(a.param(0)?)).into())
+        })),
+    );
+
+    let base = site.base_url();
+    handlebars.register_helper(
+        "absURL",
+        Box::new(ValueHelper::new("absURL", move |a| {
+            Ok(base.abs(&to_text(a.param(0)?)).into())
+        })),
+    );
+
+    let assets = Assets {
+        base: site.base_url(),
+        dir: site.assets.clone(),
+        fingerprint: site.fingerprint,
+        digests: Arc::default(),
+    };
+
+    let this = assets.clone();
+    handlebars.register_helper(
+        "asset_url",
+        Box::new(ValueHelper::new("asset_url", move |a| {
+            let (path, _) = this.find(a)?;
+            if this.fingerprint == Fingerprint::Replace {
+                return Err(format!(
+                    "\"{path}\" is only published fingerprinted, use `asset` to link to it"
+                ));
+            }
+            Ok(this.url(a, path).into())
+        })),
+    );
+
+    let this = assets.clone();
+    handlebars.register_helper(
+        "asset",
+        Box::new(ValueHelper::new("asset", move |a| {
+            let (path, file) = this.find(a)?;
+            if this.fingerprint == Fingerprint::Off {
+                return Ok(this.url(a, path).into());
+            }
+
+            let hash = this.digests.hash(&file)?;
+            let published = manifest_key(fingerprint::fingerprinted(Path::new(path), &hash));
+            Ok(this.url(a, &published).into())
+        })),
+    );
+
+    let this = assets.clone();
+    handlebars.register_helper(
+        "asset_integrity",
+        Box::new(ValueHelper::new("asset_integrity", move |a| {
+            let (_, file) = this.find(a)?;
+            this.digests.integrity(&file).map(Value::String)
+        })),
+    );
+
+    let (bundling, base) = (site.bundled, site.base_url());
+    handlebars.register_helper(
+        "bundled",
+        Box::new(ValueHelper::new("bundled", move |a| {
+            if a.params.is_empty() {
+                return Err("which stylesheets? Pass one or more names".into());
+            }
+            let stylesheets = a
+                .params
+                .iter()
+                .map(|name| {
+                    let name = to_text(name);
+                    bundled::find(&name).ok_or(format!(
+                        "no bundled stylesheet \"{name}\", try one of {}",
+                        bundled::names()
+                    ))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(bundled::markup(&stylesheets, bundling, &base).into())
+        })),
+    );
+
+    // Themes can always include the tags, whether or not a site has a favicon.
+    let (favicon, base) = (site.favicon.clone(), site.base_url());
+    handlebars.register_helper(
+        "favicons",
+        Box::new(ValueHelper::new("favicons", move |_| {
+            Ok(favicon
+                .as_deref()
+                .map(|favicon| favicons::links(favicon, &base))
+                .unwrap_or_default()
+                .into())
+        })),
+    );
+
+    let options = config.images.clone();
+    handlebars.register_helper(
+        "image",
+        Box::new(ValueHelper::new("image", move |a| {
+            let (path, file) = assets.find(a)?;
+            let Some(options) = &options else {
+                return Err("the config has no `[images]` section".into());
+            };
+            image(options, a, Path::new(path), &file, |p| {
+                assets.url(a, &manifest_key(p))
+            })
+            .map(Value::String)
+            .map_err(|e| e.to_string())
+        })),
+    );
+}
+
+/// `<img>` or `<picture>` markup for the processed copies of an image.
+fn image(
+    options: &ImageOptions,
+    a: &Args,
+    relative_path: &Path,
+    file: &Path,
+    url: impl Fn(&Path) -> String,
+) -> Result<String> {
+    if !options.processes(relative_path) {
+        bail!(
+            "\"{}\" doesn't match any of the globs in `[images] include`",
+            relative_path.display()
+        );
+    }
+
+    let variants = options.variants(relative_path, images::dimensions(file)?)?;
+    let largest = variants
+        .iter()
+        .map(|v| (v.width, v.height))
+        .max()
+        .unwrap_or_default();
+    let width = match a.hash.get("width") {
+        Some(width) => width
+            .as_u64()
+            .filter(|w| *w > 0)
+            .ok_or(eyre!("`width` must be a positive whole number"))?,
+        None => largest.0 as u64,
+    };
+    let height = (largest.1 as f64 * width as f64 / largest.0 as f64).round() as u64;
+
+    let mut attributes = vec![
+        ("alt", a.hash_str("alt").unwrap_or_default().to_owned()),
+        ("width", width.to_string()),
+        ("height", height.to_string()),
+    ];
+    match a.hash_str("sizes") {
+        Some(sizes) => attributes.push(("sizes", sizes.to_owned())),
+        None if a.hash.contains_key("width") => attributes.push(("sizes", format!("{width}px"))),
+        None => {}
+    }
+    if let Some(class) = a.hash_str("class") {
+        attributes.push(("class", class.to_owned()));
+    }
+    attributes.push((
+        "loading",
+        a.hash_str("loading").unwrap_or("lazy").to_owned(),
+    ));
+    attributes.push(("decoding", "async".to_owned()));
+
+    images::markup(&variants, url, &attributes)
+}
+
+/// What the asset helpers need to know about the site.
+#[derive(Clone)]
+struct Assets {
+    base: BaseUrl,
+    dir: Option<PathBuf>,
+    fingerprint: Fingerprint,
+    digests: Arc<Digests>,
+}
+
+/// The assets' digests, computed at most once per build
+/// however many pages link to them.
+#[derive(Default)]
+struct Digests {
+    /// Content hashes, which fingerprinted names are made from.
+    hashes: Mutex<HashMap<PathBuf, String>>,
+    /// Subresource Integrity hashes.
+    integrity: Mutex<HashMap<PathBuf, String>>,
+}
+
+impl Digests {
+    fn hash(&self, file: &Path) -> Result<String, String> {
+        Self::cached(&self.hashes, file, |f| FileRecord::of(f).map(|r| r.hash))
+    }
+
+    fn integrity(&self, file: &Path) -> Result<String, String> {
+        Self::cached(&self.integrity, file, |f| fingerprint::integrity(f))
+    }
+
+    fn cached(
+        cache: &Mutex<HashMap<PathBuf, String>>,
+        file: &Path,
+        digest: impl FnOnce(&Path) -> Result<String>,
+    ) -> Result<String, String> {
+        if let Some(digest) = cache.lock().expect("digest cache poisoned").get(file) {
+            return Ok(digest.clone());
+        }
+        // Hashed without holding the lock, so other pages' helpers
+        // aren't held up; at worst a file is hashed twice.
+        let digest = digest(file).map_err(|e| e.to_string())?;
+        cache
+            .lock()
+            .expect("digest cache poisoned")
+            .insert(file.to_owned(), digest.clone());
+        Ok(digest)
+    }
+}
+
+impl Assets {
+    /// The asset named by the helper's first parameter, as
+    /// given and as a path to the source file.
+    fn find<'a>(&self, a: &'a Args) -> Result<(&'a str, PathBuf), String> {
+        let path = a.str(0)?.trim_start_matches('/');
+        let Some(dir) = &self.dir else {
+            return Err("the site has no assets folder".into());
+        };
+        // Nothing outside the assets folder gets linked or hashed.
+        if !Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(format!("\"{path}\" isn't a path inside the assets folder"));
+        }
+
+        let file = dir.join(path);
+        if !file.is_file() {
+            return Err(format!("no asset \"{path}\" in \"{}\"", dir.display()));
+        }
+        Ok((path, file))
+    }
+
+    fn url(&self, a: &Args, path: &str) -> String {
+        match a.hash_bool("abs") {
+            true => self.base.abs(path),
+            false => self.base.rel(path),
+        }
+    }
+}
+
+/// Whether `name` belongs to a helper that ships with mllt.
+fn is_builtin(name: &str) -> bool {
+    CORE.contains(&name) || SPECIAL.contains(&name) || LIBRARY.iter().any(|(n, _)| *n == name)
+}
+
+/// A script engine for theme helpers, which can compute
+/// but can't touch the filesystem or the network, import
+/// other scripts, or run away with the build.
+pub fn script_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(1_000_000);
+    engine.set_max_call_levels(64);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(1024 * 1024);
+    engine.set_max_array_size(100_000);
+    engine.set_max_map_size(100_000);
+    engine.on_print(|s| info!("[script] {s}"));
+    engine.on_debug(|s, source, pos| debug!("[script {}{pos}] {s}", source.unwrap_or_default()));
+    engine
+}
+
+/// Register every `*.rhai` file in `dir` as a helper named after the
+/// file. Returns a hash of the scripts, so that pages can be re-rendered
+/// when they change.
+pub fn register_scripts(handlebars: &mut Handlebars, dir: impl AsRef<Path>) -> Result<String> {
+    let dir = dir.as_ref();
+    let mut hasher = blake3::Hasher::new();
+    if !dir.is_dir() {
+        return Ok(hasher.finalize().to_hex().to_string());
+    }
+
+    let mut count = 0usize;
+    for entry in WalkDir::new(dir).max_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().and_then(|s| s.to_str()) != Some("rhai")
+        {
+            continue;
+        }
+
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or(eyre!("Invalid helper script name: \"{}\"", path.display()))?;
+        if is_builtin(name) {
+            bail!(
+                "Helper script \"{}\" would replace the built-in `{name}` helper",
+                path.display()
+            );
+        }
+
+        let script = std::fs::read_to_string(path)
+            .context(format!("Error reading \"{}\"", path.display()))?;
+        handlebars
+            .register_script_helper(name, &script)
+            .map_err(|e| eyre!("{e}"))
+            .context(format!(
+                "Error compiling helper script \"{}\"",
+                path.display()
+            ))?;
+
+        hasher.update(name.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(script.as_bytes());
+        hasher.update(b"\0");
+        debug!("Registered helper script: {name}");
+        count += 1;
+    }
+
+    if count > 0 {
+        info!(
+            "Registered {count} helper script{}!",
+            if count != 1 { "s" } else { "" }
+        );
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// A helper that computes a value from its parameters, usable both
+/// as `{{name ...}}` and as a subexpression `(name ...)`.
+struct ValueHelper {
+    name: &'static str,
+    f: BoxedHelperFn,
+    /// Accept missing parameters even in strict mode.
+    lenient: bool,
+}
+
+impl ValueHelper {
+    fn new(
+        name: &'static str,
+        f: impl Fn(&Args) -> Result<Value, String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name,
+            f: Box::new(f),
+            lenient: false,
+        }
+    }
+}
+
+/// The parameters and hash a helper was called with.
+struct Args<'a> {
+    params: Vec<&'a Value>,
+    hash: Map<String, Value>,
+}
+
+impl HelperDef for ValueHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        r: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        if r.strict_mode() && !self.lenient {
+            if let Some(missing) = h.params().iter().find(|p| p.is_value_missing()) {
+                return Err(RenderError::strict_error(missing.relative_path()));
+            }
+        }
+
+        let args = Args {
+            params: h.params().iter().map(|p| p.value()).collect(),
+            hash: h
+                .hash()
+                .iter()
+                .map(|(k, v)| ((*k).to_owned(), v.value().clone()))
+                .collect(),
+        };
+
+        (self.f)(&args)
+            .map(ScopedJson::Derived)
+            .map_err(|e| RenderErrorReason::Other(format!("{}: {e}", self.name)).into())
+    }
+}
+
+impl Args<'_> {
+    fn param(&self, i: usize) -> Result<&Value, String> {
+        self.params
+            .get(i)
+            .copied()
+            .ok_or_else(|| format!("missing parameter {}", i + 1))
+    }
+
+    fn str(&self, i: usize) -> Result<&str, String> {
+        match self.param(i)? {
+            Value::String(s) => Ok(s),
+            other => Err(format!("expected a string, got {other}")),
+        }
+    }
+
+    fn num(&self, i: usize) -> Result<f64, String> {
+        match self.param(i)? {
+            Value::Number(n) => n.as_f64().ok_or_else(|| format!("{n} is out of range")),
+            Value::String(s) => s
+                .trim()
+                .parse()
+                .map_err(|_| format!("expected a number, got \"{s}\"")),
+            other => Err(format!("expected a number, got {other}")),
+        }
+    }
+
+    fn array(&self, i: usize) -> Result<&Vec<Value>, String> {
+        match self.param(i)? {
+            Value::Array(a) => Ok(a),
+            other => Err(format!("expected a list, got {other}")),
+        }
+    }
+
+    fn hash_str(&self, name: &str) -> Option<&str> {
+        self.hash.get(name).and_then(Value::as_str)
+    }
+
+    fn hash_bool(&self, name: &str) -> bool {
+        self.hash.get(name).is_some_and(is_truthy)
+    }
+}
+
+/// Handlebars' own idea of truthiness, as used by `{{#if}}`.
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+/// Look up a dotted path like `author.name` in an object.
+fn lookup<'a>(value: &'a Value, path: &str) -> &'a Value {
+    path.split('.')
+        .try_fold(value, |v, key| v.get(key))
+        .unwrap_or(&Value::Null)
+}
+
+/// Text for values joined or compared as strings.
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Whole numbers come out as integers, so `{{add 1 1}}` shows `2`, not `2.0`.
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        Number::from_f64(n).map_or(Value::Null, Value::Number)
+    }
+}
+
+/// `{{default value "fallback"}}`
+fn default(a: &Args) -> Result<Value, String> {
+    let value = a.params.first().copied().unwrap_or(&Value::Null);
+    match value {
+        Value::Null => Ok(a.param(1)?.clone()),
+        Value::String(s) if s.is_empty() => Ok(a.param(1)?.clone()),
+        _ => Ok(value.clone()),
+    }
+}
+
+/// `{{capitalize "hello world"}}` is `Hello world`.
+fn capitalize(a: &Args) -> Result<Value, String> {
+    let s = a.str(0)?;
+    let mut chars = s.chars();
+    Ok(match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>().into(),
+        None => Value::String(String::new()),
+    })
+}
+
+/// `{{truncate text 80 suffix="..."}}`, counted in characters.
+fn truncate(a: &Args) -> Result<Value, String> {
+    let s = a.str(0)?;
+    let len = a.num(1)? as usize;
+    if s.chars().count() <= len {
+        return Ok(s.into());
+    }
+
+    let suffix = a.hash_str("suffix").unwrap_or("…");
+    let truncated: String = s.chars().take(len).collect();
+    Ok(format!("{}{suffix}", truncated.trim_end()).into())
+}
+
+/// `{{join tags ", "}}`
+fn join(a: &Args) -> Result<Value, String> {
+    let separator = match a.params.get(1) {
+        Some(_) => a.str(1)?,
+        None => ", ",
+    };
+    let items: Vec<String> = a.array(0)?.iter().map(to_text).collect();
+    Ok(items.join(separator).into())
+}
+
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        // Missing values sort last.
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        (a, b) => to_text(a).cmp(&to_text(b)),
+    }
+}
+
+/// `{{#each (sort_by links "order" reverse=true)}}`
+fn sort_by(a: &Args) -> Result<Value, String> {
+    let key = a.str(1)?;
+    let mut items = a.array(0)?.clone();
+    items.sort_by(|x, y| compare(lookup(x, key), lookup(y, key)));
+    if a.hash_bool("reverse") {
+        items.reverse();
+    }
+    Ok(items.into())
+}
+
+/// `{{#each (group_by links "group")}}{{key}}: {{len items}}{{/each}}`,
+/// with groups in the order they first appear.
+fn group_by(a: &Args) -> Result<Value, String> {
+    let key = a.str(1)?;
+    let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+    for item in a.array(0)? {
+        let group = lookup(item, key);
+        match groups.iter_mut().find(|(k, _)| k == group) {
+            Some((_, items)) => items.push(item.clone()),
+            None => groups.push((group.clone(), vec![item.clone()])),
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|(key, items)| serde_json::json!({ "key": key, "items": items }))
+        .collect())
+}
+
+/// `{{#each (where links "group" "Social")}}`, or without
+/// a value to keep items where the key is truthy.
+fn filter_where(a: &Args) -> Result<Value, String> {
+    let key = a.str(1)?;
+    let wanted = a.params.get(2).copied();
+    Ok(a.array(0)?
+        .iter()
+        .filter(|item| {
+            let value = lookup(item, key);
+            match wanted {
+                Some(wanted) => value == wanted,
+                None => is_truthy(value),
+            }
+        })
+        .cloned()
+        .collect())
+}
+
+/// `{{slugify "Hello, World!"}}` is `hello-world`.
+fn slugify(s: &str) -> String {
+    let mut slug = String::with_capacity(s.len());
+    for c in s.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_owned()
+}
+
+/// `{{{json data.links pretty=true}}}`
+fn json(a: &Args) -> Result<Value, String> {
+    let value = a.param(0)?;
+    let json = if a.hash_bool("pretty") {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    json.map(Value::String).map_err(|e| e.to_string())
+}
+
+/// Everything but the characters RFC 3986 leaves unreserved.
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// `{{url_encode "a b&c"}}` is `a%20b%26c`.
+fn url_encode(a: &Args) -> Result<Value, String> {
+    let s = to_text(a.param(0)?);
+    Ok(utf8_percent_encode(&s, URL_COMPONENT).to_string().into())
+}
+
+/// `{{date page.params.date "%B %e, %Y"}}`. Takes RFC 3339 timestamps,
+/// `YYYY-MM-DD` dates, unix timestamps, or `"now"`.
+/// Date-times without a time zone are taken to be UTC.
+fn date(a: &Args) -> Result<Value, String> {
+    let format = match a.params.get(1) {
+        Some(_) => a.str(1)?,
+        None => "%Y-%m-%d",
+    };
+    let items: Vec<_> = StrftimeItems::new(format).collect();
+    if items.contains(&chrono::format::Item::Error) {
+        return Err(format!("invalid date format \"{format}\""));
+    }
+
+    let date: DateTime<Utc> = match a.param(0)? {
+        Value::String(s) if s == "now" => Utc::now(),
+        Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .map(|d| d.to_utc())
+            .or_else(|_| {
+                NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f").map(|d| d.and_utc())
+            })
+            .or_else(|_| {
+                NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .map(|d| d.and_time(Default::default()).and_utc())
+            })
+            .map_err(|_| format!("can't read \"{s}\" as a date"))?,
+        Value::Number(n) => n
+            .as_i64()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .ok_or_else(|| format!("{n} is not a valid timestamp"))?,
+        other => return Err(format!("expected a date, got {other}")),
+    };
+
+    Ok(date.format_with_items(items.into_iter()).to_string().into())
+}
+
+fn math(a: &Args, op: fn(f64, f64) -> f64) -> Result<Value, String> {
+    let (x, y) = (a.num(0)?, a.num(1)?);
+    let result = op(x, y);
+    if !result.is_finite() {
+        return Err(format!("{x} and {y} don't give a number"));
+    }
+    Ok(number(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Render `template` against `data` with every built-in helper.
+    fn render(template: &str, data: Value) -> Result<String, String> {
+        let mut handlebars = Handlebars::new();
+        handlebars.set_strict_mode(true);
+        register(&mut handlebars, &Config::default());
+        handlebars
+            .render_template(template, &data)
+            .map_err(|e| e.to_string())
+    }
+
+    fn ok(template: &str) -> String {
+        render(template, json!({})).unwrap()
+    }
+
+    fn links() -> Value {
+        json!({ "links": [
+            { "name": "b", "group": "Social", "order": 2 },
+            { "name": "a", "group": "Code", "order": 10 },
+            { "name": "c", "group": "Social" },
+            { "name": "d", "group": "Code", "order": 1, "hidden": true },
+        ]})
+    }
+
+    fn names(template: &str) -> String {
+        render(template, links()).unwrap()
+    }
+
+    #[test]
+    fn truncate() {
+        assert_eq!(ok(r#"{{truncate "hello world" 5}}"#), "hello…");
+        assert_eq!(ok(r#"{{truncate "hello world" 6 suffix="..."}}"#), "hello...");
+        assert_eq!(ok(r#"{{truncate "hello" 5}}"#), "hello");
+        assert_eq!(ok(r#"{{truncate "" 5}}"#), "");
+        // Characters, not bytes.
+        assert_eq!(ok(r#"{{truncate "héllo wörld" 4}}"#), "héll…");
+        assert_eq!(ok(r#"{{truncate "日本語のテキスト" 3}}"#), "日本語…");
+        assert!(render(r#"{{truncate "hello" "many"}}"#, json!({})).is_err());
+    }
+
+    #[test]
+    fn slugify() {
+        assert_eq!(ok(r#"{{slugify "Hello, World!"}}"#), "hello-world");
+        assert_eq!(ok(r#"{{slugify "  --Already-slugged--  "}}"#), "already-slugged");
+        assert_eq!(ok(r#"{{slugify "Ærøskøbing Über"}}"#), "ærøskøbing-über");
+        assert_eq!(ok(r#"{{slugify ""}}"#), "");
+        assert_eq!(ok(r#"{{slugify "!!!"}}"#), "");
+    }
+
+    #[test]
+    fn date() {
+        assert_eq!(ok(r#"{{date "2024-05-02"}}"#), "2024-05-02");
+        assert_eq!(
+            ok(r#"{{date "2024-05-02T10:30:00+02:00" "%H:%M"}}"#),
+            "08:30"
+        );
+        assert_eq!(ok(r#"{{date "2024-05-02T10:30:00" "%H:%M"}}"#), "10:30");
+        assert_eq!(ok(r#"{{date 0 "%Y"}}"#), "1970");
+        assert!(ok(r#"{{date "now" "%Y"}}"#).starts_with("20"));
+
+        assert!(render(r#"{{date "yesterday"}}"#, json!({})).is_err());
+        assert!(render(r#"{{date "2024-05-02" "%Q"}}"#, json!({})).is_err());
+        let e = render("{{date page.params.date}}", json!({ "page": {} })).unwrap_err();
+        assert!(e.contains("page.params.date"), "{e}");
+    }
+
+    #[test]
+    fn sort_by() {
+        assert_eq!(names(r#"{{#each (sort_by links "name")}}{{name}}{{/each}}"#), "abcd");
+        // Missing keys sort last, numbers by value rather than as text.
+        assert_eq!(names(r#"{{#each (sort_by links "order")}}{{name}}{{/each}}"#), "dbac");
+        assert_eq!(
+            names(r#"{{#each (sort_by links "order" reverse=true)}}{{name}}{{/each}}"#),
+            "cabd"
+        );
+        assert_eq!(names(r#"{{#each (sort_by links "nope")}}{{name}}{{/each}}"#), "bacd");
+        assert!(render(r#"{{sort_by "links" "name"}}"#, links()).is_err());
+    }
+
+    #[test]
+    fn group_by() {
+        assert_eq!(
+            names(
+                r#"{{#each (group_by links "group")}}{{key}}:{{#each items}}{{name}}{{/each}} {{/each}}"#
+            ),
+            "Social:bc Code:ad "
+        );
+        assert_eq!(
+            names(r#"{{#each (group_by links "hidden")}}{{key}}:{{len items}} {{/each}}"#),
+            ":3 true:1 "
+        );
+        assert_eq!(
+            render(r#"{{len (group_by links "group")}}"#, json!({ "links": [] })).unwrap(),
+            "0"
+        );
+    }
+
+    #[test]
+    fn filter_where() {
+        assert_eq!(
+            names(r#"{{#each (where links "group" "Code")}}{{name}}{{/each}}"#),
+            "ad"
+        );
+        assert_eq!(names(r#"{{#each (where links "hidden")}}{{name}}{{/each}}"#), "d");
+        assert_eq!(names(r#"{{#each (where links "order" 2)}}{{name}}{{/each}}"#), "b");
+        assert_eq!(names(r#"{{len (where links "nope" "x")}}"#), "0");
+    }
+
+    #[test]
+    fn math() {
+        assert_eq!(ok("{{add 1 2}}"), "3");
+        assert_eq!(ok("{{sub 1 2.5}}"), "-1.5");
+        assert_eq!(ok(r#"{{mul "3" 4}}"#), "12");
+        assert_eq!(ok("{{div 7 2}}"), "3.5");
+        assert_eq!(ok("{{mod 7 3}}"), "1");
+        assert_eq!(ok("{{min 7 3}} {{max 7 3}}"), "3 7");
+        assert_eq!(ok("{{round 2.5}} {{floor 2.5}} {{ceil 2.1}}"), "3 2 3");
+        assert_eq!(ok("{{add (mul 2 3) 1}}"), "7");
+
+        for template in ["{{div 1 0}}", "{{mod 1 0}}", r#"{{add 1 "one"}}"#, "{{add 1}}"] {
+            assert!(render(template, json!({})).is_err(), "{template}");
+        }
+        let e = render("{{add page.missing 1}}", json!({ "page": {} })).unwrap_err();
+        assert!(e.contains("page.missing"), "{e}");
+    }
+
+    #[test]
+    fn digests_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("style.css");
+        std::fs::write(&file, "a").unwrap();
+
+        let digests = Digests::default();
+        let (hash, integrity) = (digests.hash(&file).unwrap(), digests.integrity(&file).unwrap());
+        assert!(integrity.starts_with("sha384-"));
+
+        // Within a build, the first digest sticks.
+        std::fs::write(&file, "b").unwrap();
+        assert_eq!(digests.hash(&file).unwrap(), hash);
+        assert_eq!(digests.integrity(&file).unwrap(), integrity);
+        assert_ne!(Digests::default().hash(&file).unwrap(), hash);
+    }
+
+    #[test]
+    fn assets_stay_inside_their_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = dir.path().join("assets");
+        std::fs::create_dir(&assets).unwrap();
+        std::fs::write(assets.join("a.css"), "a").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "b").unwrap();
+
+        let mut config = Config::default();
+        config.site.assets = Some(assets.clone());
+        let mut handlebars = Handlebars::new();
+        register(&mut handlebars, &config);
+        let render = |template: &str| handlebars.render_template(template, &json!({}));
+
+        assert_eq!(render(r#"{{asset_url "/a.css"}}"#).unwrap(), "/a.css");
+        // Absolute paths are taken as relative to the assets folder.
+        let secret = dir.path().join("secret.txt").display().to_string();
+        assert!(render(&format!("{{{{asset_url \"{secret}\"}}}}")).is_err());
+        for path in ["../secret.txt", "./../secret.txt", "a.css/../../secret.txt"] {
+            for helper in ["asset_url", "asset", "asset_integrity"] {
+                let template = format!("{{{{{helper} \"{path}\"}}}}");
+                let e = render(&template).unwrap_err().to_string();
+                assert!(e.contains("isn't a path inside the assets folder"), "{template}: {e}");
+            }
+        }
+    }
+
+    /// Render `template` with every built-in helper and the helper
+    /// scripts `scripts`, given as `(file name, source)`.
+    fn render_scripted(scripts: &[(&str, &str)], template: &str) -> Result<String> {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, source) in scripts {
+            std::fs::write(dir.path().join(name), source).unwrap();
+        }
+
+        let mut handlebars = Handlebars::new();
+        handlebars.set_strict_mode(true);
+        handlebars.set_engine(script_engine());
+        register(&mut handlebars, &Config::default());
+        register_scripts(&mut handlebars, dir.path())?;
+        Ok(handlebars.render_template(template, &json!({}))?)
+    }
+
+    #[test]
+    fn script_helpers() {
+        let scripts = [
+            ("shout.rhai", "params[0].to_upper() + \"!\""),
+            ("notes.txt", "not a script"),
+        ];
+        assert_eq!(render_scripted(&scripts, "{{shout \"hi\"}}").unwrap(), "HI!");
+        assert!(render_scripted(&scripts, "{{notes}}").is_err());
+    }
+
+    #[test]
+    fn scripts_cant_replace_builtins() {
+        let e = render_scripted(&[("upper.rhai", "params[0]")], "").unwrap_err();
+        assert!(format!("{e:#}").contains("built-in `upper` helper"), "{e:#}");
+    }
+
+    #[test]
+    fn scripts_cant_run_forever() {
+        let scripts = [("spin.rhai", "loop { }")];
+        let e = render_scripted(&scripts, "{{spin}}").unwrap_err();
+        assert!(format!("{e:#}").contains("Too many operations"), "{e:#}");
+    }
+
+    #[test]
+    fn scripts_cant_import() {
+        let scripts = [("load.rhai", "import \"other\" as other; other::value")];
+        let e = render_scripted(&scripts, "{{load}}").unwrap_err();
+        assert!(format!("{e:#}").contains("Module not found"), "{e:#}");
+    }
+}
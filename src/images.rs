@@ -0,0 +1,667 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Resized and re-encoded copies of the site's images, made during
+//! the asset step and linked to with the `image` helper.
+
+use color_eyre::eyre::{bail, eyre, Context as _, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use handlebars::html_escape;
+use hashbrown::HashSet;
+use image::{
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    metadata::Orientation,
+    DynamicImage, ImageDecoder, ImageReader,
+};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    borrow::Cow,
+    io::{BufRead, BufWriter, Cursor, Seek},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use tempfile::NamedTempFile;
+use tracing::{debug, info};
+use walkdir::WalkDir;
+
+use crate::manifest::FileRecord;
+
+/// The `[images]` section of the config.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ImageOptions {
+    /// Widths in pixels to make copies at. Images are never scaled up.
+    #[serde(deserialize_with = "widths")]
+    pub widths: Vec<u32>,
+
+    /// Formats to also encode every copy as, besides the image's own.
+    /// WebP copies are lossless, so JPEG photos, which they'd mostly
+    /// make larger, don't get them.
+    #[serde(default)]
+    pub formats: Vec<Format>,
+
+    /// Quality of the lossy formats, JPEG and AVIF, from 1 to 100.
+    #[serde(default = "default_quality", deserialize_with = "quality")]
+    pub quality: u8,
+
+    /// Globs, relative to the assets folder, of the images to process.
+    /// Only their processed copies are published, not the originals.
+    #[serde(default = "default_include")]
+    pub include: Include,
+
+    /// Where processed images are kept between builds.
+    #[serde(default = "default_cache")]
+    pub cache: PathBuf,
+}
+
+/// A modern format that copies can be encoded as.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Webp,
+    Avif,
+}
+
+fn default_quality() -> u8 {
+    80
+}
+
+fn default_include() -> Include {
+    Include::new(
+        ["**/*.jpg", "**/*.jpeg", "**/*.png", "**/*.webp"]
+            .map(String::from)
+            .to_vec(),
+    )
+    .expect("default image globs are valid")
+}
+
+/// The `include` globs, compiled once when the config is loaded.
+/// Matching ignores case, like `photo.JPG` camera file names.
+#[derive(Debug, Clone)]
+pub struct Include {
+    globs: Vec<String>,
+    set: GlobSet,
+}
+
+impl Include {
+    pub fn new(globs: Vec<String>) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for glob in &globs {
+            builder.add(
+                GlobBuilder::new(glob)
+                    .case_insensitive(true)
+                    .build()
+                    .context(format!("Invalid image glob \"{glob}\""))?,
+            );
+        }
+        let set = builder.build()?;
+        Ok(Self { globs, set })
+    }
+
+    pub fn is_match(&self, relative_path: &Path) -> bool {
+        self.set.is_match(relative_path)
+    }
+}
+
+impl PartialEq for Include {
+    fn eq(&self, other: &Self) -> bool {
+        self.globs == other.globs
+    }
+}
+
+impl Eq for Include {}
+
+impl Serialize for Include {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.globs.serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for Include {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        Include::new(Vec::<String>::deserialize(d)?)
+            .map_err(|e| serde::de::Error::custom(format!("{e:#}")))
+    }
+}
+
+fn default_cache() -> PathBuf {
+    "./.mllt-cache/images".into()
+}
+
+fn widths<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u32>, D::Error> {
+    let widths = Vec::<u32>::deserialize(d)?;
+    if widths.is_empty() || widths.contains(&0) {
+        return Err(serde::de::Error::custom(
+            "`widths` must list at least one width, none of them 0",
+        ));
+    }
+    Ok(widths)
+}
+
+fn quality<'de, D: Deserializer<'de>>(d: D) -> Result<u8, D::Error> {
+    match u8::deserialize(d)? {
+        q @ 1..=100 => Ok(q),
+        q => Err(serde::de::Error::custom(format!(
+            "`quality` must be between 1 and 100, got {q}"
+        ))),
+    }
+}
+
+/// How a processed copy is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Jpeg,
+    Png,
+    Webp,
+    Avif,
+}
+
+impl Encoding {
+    /// The encoding of a source image, going by its extension.
+    fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Webp => "webp",
+            Self::Avif => "avif",
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Webp => "image/webp",
+            Self::Avif => "image/avif",
+        }
+    }
+}
+
+impl From<Format> for Encoding {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Webp => Self::Webp,
+            Format::Avif => Self::Avif,
+        }
+    }
+}
+
+/// One processed copy of an image.
+#[derive(Debug, Clone)]
+pub struct Variant {
+    /// Where the copy ends up, relative to the output folder.
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub encoding: Encoding,
+}
+
+impl ImageOptions {
+    /// Whether the image at `relative_path` in the assets folder is processed.
+    pub fn processes(&self, relative_path: &Path) -> bool {
+        self.include.is_match(relative_path)
+    }
+
+    /// Every copy made of the image at `relative_path` in the assets
+    /// folder, given its size. Modern formats come first, so they're
+    /// listed first in `<picture>` tags.
+    pub fn variants(
+        &self,
+        relative_path: &Path,
+        (width, height): (u32, u32),
+    ) -> Result<Vec<Variant>> {
+        let source = Encoding::of(relative_path).ok_or(eyre!(
+            "Can't process \"{}\": only JPEG, PNG and WebP images are supported.",
+            relative_path.display()
+        ))?;
+
+        let mut widths: Vec<u32> = self
+            .widths
+            .iter()
+            .copied()
+            .filter(|w| *w <= width)
+            .collect();
+        if widths.is_empty() {
+            widths.push(width);
+        }
+        widths.sort_unstable();
+        widths.dedup();
+
+        // Lossless WebP copies of photos would mostly be larger than
+        // the JPEG, yet browsers would pick them for coming first.
+        let lossless_photo = |e: Encoding| e == Encoding::Webp && source == Encoding::Jpeg;
+        let mut encodings: Vec<Encoding> = Vec::new();
+        for encoding in self.formats.iter().map(|f| Encoding::from(*f)) {
+            if encoding != source && !encodings.contains(&encoding) && !lossless_photo(encoding) {
+                encodings.push(encoding);
+            }
+        }
+        encodings.push(source);
+
+        let stem = relative_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let mut variants = Vec::new();
+        for encoding in encodings {
+            for &w in &widths {
+                variants.push(Variant {
+                    path: relative_path
+                        .with_file_name(format!("{stem}.{w}w.{}", encoding.extension())),
+                    width: w,
+                    height: ((height as f64 * w as f64 / width as f64).round() as u32).max(1),
+                    encoding,
+                });
+            }
+        }
+        Ok(variants)
+    }
+}
+
+/// Size of the image at `path`, after applying its EXIF orientation.
+pub fn dimensions(path: &Path) -> Result<(u32, u32)> {
+    let reader = std::io::BufReader::new(
+        std::fs::File::open(path).context(format!("Error opening \"{}\"", path.display()))?,
+    );
+    size(reader).context(format!("Error reading \"{}\"", path.display()))
+}
+
+fn size(reader: impl BufRead + Seek) -> Result<(u32, u32)> {
+    let mut decoder = ImageReader::new(reader)
+        .with_guessed_format()?
+        .into_decoder()?;
+    let (width, height) = decoder.dimensions();
+    Ok(match decoder.orientation()? {
+        Orientation::Rotate90
+        | Orientation::Rotate270
+        | Orientation::Rotate90FlipH
+        | Orientation::Rotate270FlipH => (height, width),
+        _ => (width, height),
+    })
+}
+
+/// Decode an image, turned upright. EXIF metadata is dropped here,
+/// since none of the encoders write it back out.
+fn decode(bytes: &[u8]) -> Result<DynamicImage> {
+    let mut decoder = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn encode(image: &DynamicImage, variant: &Variant, quality: u8, path: &Path) -> Result<()> {
+    let image = if image.width() == variant.width && image.height() == variant.height {
+        Cow::Borrowed(image)
+    } else {
+        Cow::Owned(image.resize_exact(variant.width, variant.height, FilterType::Lanczos3))
+    };
+
+    // Write to a temporary file first so an interrupted build
+    // never leaves a truncated image in the cache. Identical images
+    // are encoded in parallel, so each gets a file of its own.
+    let mut partial = NamedTempFile::new_in(path.parent().unwrap_or(Path::new(".")))?;
+    let mut out = BufWriter::new(partial.as_file_mut());
+    match variant.encoding {
+        Encoding::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, quality)),
+        Encoding::Png => image.write_with_encoder(PngEncoder::new(&mut out)),
+        Encoding::Webp => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut out)),
+        Encoding::Avif => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(AvifEncoder::new_with_speed_quality(&mut out, 8, quality)),
+    }?;
+    out.into_inner().map_err(|e| e.into_error())?;
+    partial.persist(path)?;
+
+    Ok(())
+}
+
+/// Make every copy of every image in `src` that `options` asks for,
+/// reusing ones already in the cache, and put them into `dst`.
+/// Returns the paths of all copies, relative to `dst`.
+pub fn process(
+    options: &ImageOptions,
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+) -> Result<HashSet<PathBuf>> {
+    let src = src.as_ref();
+    let dst = dst.as_ref();
+    std::fs::create_dir_all(&options.cache).context(format!(
+        "Error creating image cache \"{}\"",
+        options.cache.display()
+    ))?;
+
+    let mut sources = Vec::new();
+    for entry in WalkDir::new(src) {
+        let entry = entry?;
+        let relative_path = entry.path().strip_prefix(src)?;
+        if entry.file_type().is_file() && options.processes(relative_path) {
+            sources.push(relative_path.to_owned());
+        }
+    }
+
+    info!("Processing images...");
+    let encoded = AtomicUsize::new(0);
+    let reused = AtomicUsize::new(0);
+    let outputs = sources
+        .par_iter()
+        .map(|relative_path| {
+            let src_path = src.join(relative_path);
+            let bytes = std::fs::read(&src_path)
+                .context(format!("Error reading \"{}\"", src_path.display()))?;
+
+            // Cached copies are named after the source's contents and
+            // the options that affect them, so edits never reuse one.
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(&bytes);
+            hasher.update(&[options.quality]);
+            let key = hasher.finalize().to_hex();
+            let key = &key[..16];
+
+            let mut image = None;
+            let mut outputs = Vec::new();
+            let size = size(Cursor::new(&bytes))
+                .context(format!("Error reading \"{}\"", src_path.display()))?;
+            for variant in options.variants(relative_path, size)? {
+                let cached = options.cache.join(format!(
+                    "{key}.{}w.{}",
+                    variant.width,
+                    variant.encoding.extension()
+                ));
+
+                if cached.is_file() {
+                    reused.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    let image = match &image {
+                        Some(image) => image,
+                        None => image.insert(
+                            decode(&bytes)
+                                .context(format!("Error decoding \"{}\"", src_path.display()))?,
+                        ),
+                    };
+                    encode(image, &variant, options.quality, &cached)
+                        .context(format!("Error encoding \"{}\"", variant.path.display()))?;
+                    encoded.fetch_add(1, Ordering::Relaxed);
+                    debug!("Encoded: {}", cached.display());
+                }
+
+                let dst_path = dst.join(&variant.path);
+                if is_stale(&cached, &dst_path)? {
+                    if let Some(parent) = dst_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::copy(&cached, &dst_path)?;
+                    debug!("Copied: {}", dst_path.display());
+                }
+                outputs.push(variant.path);
+            }
+
+            Ok(outputs)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let encoded = encoded.into_inner();
+    info!(
+        "Processed {} image{}: encoded {encoded} cop{}, reused {} from the cache.",
+        sources.len(),
+        if sources.len() != 1 { "s" } else { "" },
+        if encoded != 1 { "ies" } else { "y" },
+        reused.into_inner()
+    );
+
+    Ok(outputs.into_iter().flatten().collect())
+}
+
+/// Whether `dst` is missing or differs from `src`. Contents are
+/// compared rather than times, since reverting an image brings back
+/// a cached copy older than the one published in the meantime.
+fn is_stale(src: &Path, dst: &Path) -> Result<bool> {
+    let Ok(published) = std::fs::metadata(dst) else {
+        return Ok(true);
+    };
+    if published.len() != std::fs::metadata(src)?.len() {
+        return Ok(true);
+    }
+    Ok(FileRecord::of(src)?.hash != FileRecord::of(dst)?.hash)
+}
+
+/// HTML for the `image` helper: an `<img>` with a `srcset` of every
+/// width, wrapped in a `<picture>` if there are modern formats too.
+pub fn markup(
+    variants: &[Variant],
+    url: impl Fn(&Path) -> String,
+    attributes: &[(&str, String)],
+) -> Result<String> {
+    let Some(fallback) = variants.last() else {
+        bail!("no image variants to link to");
+    };
+
+    let srcset = |encoding: Encoding| {
+        variants
+            .iter()
+            .filter(|v| v.encoding == encoding)
+            .map(|v| format!("{} {}w", url(&v.path), v.width))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let sizes = attributes
+        .iter()
+        .find(|(name, _)| *name == "sizes")
+        .map(|(_, sizes)| format!(r#" sizes="{}""#, html_escape(sizes)))
+        .unwrap_or_default();
+
+    let mut img = format!(
+        r#"<img src="{}" srcset="{}""#,
+        html_escape(&url(&fallback.path)),
+        html_escape(&srcset(fallback.encoding))
+    );
+    for (name, value) in attributes {
+        img.push_str(&format!(r#" {name}="{}""#, html_escape(value)));
+    }
+    img.push('>');
+
+    let mut sources = String::new();
+    let mut seen = vec![fallback.encoding];
+    for variant in variants {
+        if seen.contains(&variant.encoding) {
+            continue;
+        }
+        seen.push(variant.encoding);
+        sources.push_str(&format!(
+            r#"<source type="{}" srcset="{}"{sizes}>"#,
+            variant.encoding.mime(),
+            html_escape(&srcset(variant.encoding))
+        ));
+    }
+
+    Ok(match sources.is_empty() {
+        true => img,
+        false => format!("<picture>{sources}{img}</picture>"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(toml: &str) -> ImageOptions {
+        toml::from_str(toml).unwrap()
+    }
+
+    /// `(file name, width, height)` of every variant.
+    fn variants(options: &ImageOptions, path: &str, size: (u32, u32)) -> Vec<(String, u32, u32)> {
+        options
+            .variants(Path::new(path), size)
+            .unwrap()
+            .into_iter()
+            .map(|v| (v.path.display().to_string(), v.width, v.height))
+            .collect()
+    }
+
+    #[test]
+    fn never_scaled_up() {
+        let options = options("widths = [400, 800, 1600]");
+        let widths: Vec<u32> = variants(&options, "a.jpg", (1000, 500))
+            .into_iter()
+            .map(|(_, w, _)| w)
+            .collect();
+        assert_eq!(widths, [400, 800]);
+
+        // Smaller than every width, so it's only re-encoded at its own size.
+        assert_eq!(
+            variants(&options, "photos/small.png", (300, 200)),
+            [("photos/small.300w.png".to_owned(), 300, 200)]
+        );
+    }
+
+    #[test]
+    fn widths_are_sorted_and_deduplicated() {
+        let options = options("widths = [800, 400, 800]");
+        let widths: Vec<u32> = variants(&options, "a.jpg", (1000, 500))
+            .into_iter()
+            .map(|(_, w, _)| w)
+            .collect();
+        assert_eq!(widths, [400, 800]);
+    }
+
+    #[test]
+    fn modern_formats_come_first() {
+        let options = options("widths = [100]\nformats = [\"avif\", \"webp\", \"avif\"]");
+        let names: Vec<String> = variants(&options, "a.jpg", (200, 200))
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect();
+        // Lossless WebP would mostly make photos larger.
+        assert_eq!(names, ["a.100w.avif", "a.100w.jpg"]);
+        let names: Vec<String> = variants(&options, "a.png", (200, 200))
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect();
+        assert_eq!(names, ["a.100w.avif", "a.100w.webp", "a.100w.png"]);
+
+        // A WebP source isn't encoded as WebP twice.
+        let names: Vec<String> = variants(&options, "b.webp", (200, 200))
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect();
+        assert_eq!(names, ["b.100w.avif", "b.100w.webp"]);
+    }
+
+    #[test]
+    fn heights_are_rounded() {
+        let options = options("widths = [100, 2]");
+        assert_eq!(
+            variants(&options, "a.png", (1000, 333)),
+            [
+                ("a.2w.png".to_owned(), 2, 1),
+                ("a.100w.png".to_owned(), 100, 33)
+            ]
+        );
+        assert_eq!(variants(&options, "b.png", (300, 200))[1].2, 67);
+    }
+
+    #[test]
+    fn unsupported_source() {
+        let options = options("widths = [100]");
+        assert!(options.variants(Path::new("a.gif"), (200, 200)).is_err());
+    }
+
+    #[test]
+    fn include_ignores_case() {
+        let options = options("widths = [100]");
+        assert!(options.processes(Path::new("photo.JPG")));
+        assert!(options.processes(Path::new("scans/IMG.PNG")));
+        assert!(options.processes(Path::new("a.Jpeg")));
+        assert!(!options.processes(Path::new("logo.svg")));
+
+        let options = self::options("widths = [100]\ninclude = [\"photos/*.jpg\"]");
+        assert!(options.processes(Path::new("Photos/A.JPG")));
+        assert!(!options.processes(Path::new("a.jpg")));
+    }
+
+    #[test]
+    fn invalid_options_fail_to_load() {
+        assert!(toml::from_str::<ImageOptions>("widths = [100]\ninclude = [\"[\"]").is_err());
+        assert!(toml::from_str::<ImageOptions>("widths = []").is_err());
+        assert!(toml::from_str::<ImageOptions>("widths = [100]\nquality = 0").is_err());
+    }
+
+    #[test]
+    fn identical_images_are_processed_together() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("assets");
+        std::fs::create_dir(&src).unwrap();
+        let image = DynamicImage::new_rgb8(8, 4);
+        for name in ["a.png", "b.png", "c.png", "d.png"] {
+            image.save(src.join(name)).unwrap();
+        }
+
+        let mut options = options("widths = [4, 8]\nformats = [\"webp\"]");
+        options.cache = dir.path().join("cache");
+        let dst = dir.path().join("output");
+        let outputs = process(&options, &src, &dst).unwrap();
+        assert_eq!(outputs.len(), 16);
+        for output in outputs {
+            assert!(dst.join(output).is_file());
+        }
+
+        // Only the finished copies are left in the cache.
+        let cached: Vec<_> = std::fs::read_dir(&options.cache)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert_eq!(cached.len(), 4, "{cached:?}");
+        assert_eq!(dimensions(&dst.join("a.4w.png")).unwrap(), (4, 2));
+    }
+
+    #[test]
+    fn reverted_images_are_published_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("assets");
+        std::fs::create_dir(&src).unwrap();
+        let mut options = options("widths = [4]");
+        options.cache = dir.path().join("cache");
+        let dst = dir.path().join("output");
+
+        let color = |rgb: [u8; 3]| {
+            let image = image::RgbImage::from_pixel(4, 4, image::Rgb(rgb));
+            image.save(src.join("a.png")).unwrap();
+            process(&options, &src, &dst).unwrap();
+            image::open(dst.join("a.4w.png")).unwrap().to_rgb8().get_pixel(0, 0).0
+        };
+        assert_eq!(color([255, 0, 0]), [255, 0, 0]);
+        assert_eq!(color([0, 0, 255]), [0, 0, 255]);
+        // Its copy is back from the cache, older than the blue one.
+        assert_eq!(color([255, 0, 0]), [255, 0, 0]);
+    }
+}
@@ -17,18 +17,33 @@
 */
 
 use clap::Parser;
-use cli::{Cli, Command};
+use cli::{Cli, Command, ThemeCommand};
 use color_eyre::eyre::Result;
 use config::Config;
 use new::instantiate_site;
 use site::Site;
-use std::time::Instant;
+use std::{borrow::Cow, path::Path, time::Instant};
 use tracing::{debug, info};
 
+mod bundled;
 mod cli;
 mod config;
+mod data;
+mod deps;
+mod favicons;
+mod fingerprint;
+mod frontmatter;
+mod helpers;
+mod images;
+mod manifest;
+mod markdown;
 mod new;
+mod platforms;
+mod prune;
+mod serve;
 mod site;
+mod theme;
+mod url;
 
 fn main() -> Result<()> {
     // Startup initialization. Pretty print errors to console,
@@ -39,31 +54,73 @@ fn main() -> Result<()> {
     debug!("Strike the Earth!");
 
     match &cli.command {
-        Command::New { force, base_path } => {
-            instantiate_site(base_path, *force)
+        Command::New { force, theme, from, base_url, yes, base_path } => match from {
+            Some(from) => new::instantiate_from(base_path, *force, from, base_url.as_deref()),
+            None => instantiate_site(base_path, *force, theme, base_url.as_deref(), *yes),
+        },
+        Command::Theme { command: ThemeCommand::List } => {
+            new::list_themes();
+            Ok(())
         }
-        Command::Serve { port: _port, .. } => {
-            // Add server logic here
-            todo!()
+        Command::Serve { port, site } => {
+            serve::serve(site, &cli, *port)
         }
-        Command::Build { config, .. } => {
+        Command::Build { site, dry_run, .. } => {
             // Some CLI flags overwrite config file options.
             // merge_with applies this into one, single config struct.
-            render(&Config::from_file(config.as_path())?.merge_with(&cli))
+            let config = Config::from_file(site.config.as_path())?.merge_with(&cli);
+            render(&config, site.config.as_path(), *dry_run)
         },
     }
 }
 
-fn render(config: &Config) -> Result<()> {
+fn render(config: &Config, config_path: &Path, dry_run: bool) -> Result<()> {
     let now = Instant::now();
 
     debug!("Final Config: {config:#?}");
 
+    // Checked before anything is written, in case the
+    // output folder is where the site's sources are.
+    if config.site.prune || dry_run {
+        // The project is the folder holding the config file.
+        let root = match config_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        prune::check_out_dir(&config.site.out_dir, root, &config.source_dirs()?)?;
+    }
+
+    // A dry run builds into a throwaway folder, so that the real
+    // output is left alone and only compared against.
+    let temp_dir = if dry_run {
+        Some(tempfile::Builder::new().prefix("mllt-dry-run-").tempdir()?)
+    } else {
+        None
+    };
+    let build_config = match &temp_dir {
+        Some(temp_dir) => {
+            let mut config = config.clone();
+            config.site.out_dir = temp_dir.path().to_owned();
+            Cow::Owned(config)
+        }
+        None => Cow::Borrowed(config),
+    };
+
     // Perform the render.
-    info!("Building site to \"{}\"", config.site.out_dir.display());
-    let mut site = Site::new(config)?;
+    info!("Building site to \"{}\"", build_config.site.out_dir.display());
+    let mut site = Site::new(&build_config)?;
     site.reload_templates()?;
-    site.render()?;
+    let produced = site.render()?;
+
+    if config.site.prune || dry_run {
+        let stale = prune::prune(&config.site.out_dir, &produced, &config.site.protect, dry_run)?;
+        if dry_run {
+            info!("Pruning would remove {} stale file{}.", stale.len(), if stale.len() != 1 { "s" } else { "" });
+        } else {
+            info!("Pruned {} stale file{}.", stale.len(), if stale.len() != 1 { "s" } else { "" });
+        }
+    }
+
     info!("Done! Took {}", format_duration(now.elapsed())?);
     Ok(())
 }
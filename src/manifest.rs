@@ -0,0 +1,121 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use color_eyre::eyre::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, warn};
+
+/// File name of the build manifest, stored in the root of the output folder.
+pub const MANIFEST_FILE: &str = ".mllt-manifest.json";
+
+/// A record of what previous builds wrote to the output folder, used
+/// to decide what needs to be redone based on content rather than on
+/// file modification times.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BuildManifest {
+    /// Copied assets, keyed by their path relative to the assets folder.
+    /// Their copies are at the same path in the output folder, or at
+    /// its fingerprinted name, which the hash recorded here gives.
+    #[serde(default)]
+    pub assets: BTreeMap<String, FileRecord>,
+
+    /// Fingerprints of the inputs each rendered page was built
+    /// from, keyed by its path relative to the output folder.
+    #[serde(default)]
+    pub pages: BTreeMap<String, String>,
+
+    /// Hash of the `favicon` source the icon set was generated from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+}
+
+/// The identity of a file's contents.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileRecord {
+    /// Hex-encoded BLAKE3 hash of the file.
+    pub hash: String,
+    pub size: u64,
+}
+
+impl FileRecord {
+    pub fn of(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = std::fs::File::open(path)
+            .context(format!("Error opening: \"{}\"", path.display()))?;
+
+        let mut hasher = blake3::Hasher::new();
+        let mut buf = [0u8; 64 * 1024];
+        let mut size = 0u64;
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            size += n as u64;
+        }
+
+        Ok(Self {
+            hash: hasher.finalize().to_hex().to_string(),
+            size,
+        })
+    }
+}
+
+impl BuildManifest {
+    pub fn path(out_dir: impl AsRef<Path>) -> PathBuf {
+        out_dir.as_ref().join(MANIFEST_FILE)
+    }
+
+    /// Load the manifest from `out_dir`. A missing or unreadable
+    /// manifest just means everything gets rebuilt.
+    pub fn load(out_dir: impl AsRef<Path>) -> Self {
+        let path = Self::path(out_dir);
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            debug!("No build manifest at \"{}\", starting fresh.", path.display());
+            return Self::default();
+        };
+
+        match serde_json::from_str(&contents) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                warn!("Ignoring unreadable build manifest \"{}\": {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, out_dir: impl AsRef<Path>) -> Result<()> {
+        std::fs::create_dir_all(out_dir.as_ref())?;
+        std::fs::write(Self::path(out_dir), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Converts a path relative to the output folder into a manifest key.
+pub fn manifest_key(relative_path: impl AsRef<Path>) -> String {
+    relative_path
+        .as_ref()
+        .to_string_lossy()
+        .replace(std::path::MAIN_SEPARATOR, "/")
+}
@@ -17,22 +17,109 @@
 */
 
 use color_eyre::eyre::{bail, eyre, Result};
-use serde::Serialize;
-use std::{fs::File, io::Write, path::Path};
+use std::{
+    fs::File,
+    io::{IsTerminal, Write},
+    path::Path,
+};
 use tracing::warn;
 
 use crate::config::Config;
+use themes::StarterTheme;
 
-pub fn instantiate_site(base_path: impl AsRef<Path>, clobber: bool) -> Result<()> {
+mod template;
+mod themes;
+mod wizard;
+
+pub use themes::DEFAULT as DEFAULT_THEME;
+
+/// Keeps the image cache, which `[images] cache` puts
+/// here by default, out of the site's repository.
+const GITIGNORE: &str = "/.mllt-cache/\n";
+
+/// Create a site from a starter theme. Unless `assume_yes` is set or
+/// there's no terminal to ask in, the user is asked how to set it up,
+/// with `theme` and `base_url` as the defaults.
+pub fn instantiate_site(
+    base_path: impl AsRef<Path>,
+    clobber: bool,
+    theme: &str,
+    base_url: Option<&str>,
+    assume_yes: bool,
+) -> Result<()> {
     let base_path = base_path.as_ref();
+    check_base_url(base_url)?;
+    let theme = themes::find(theme).ok_or(eyre!(
+        "Unknown starter theme \"{theme}\", expected one of {}. See `mllt theme list`.",
+        themes::names()
+    ))?;
+
+    // Before asking anything, so the answers aren't wasted on a
+    // folder that can't be used.
     create_project_dir(base_path, clobber)?;
-    write_serde_default::<Config>(base_path.join("mllt.toml"), clobber)?;
-    create_sample_theme(base_path.join("theme"), clobber)?;
-    create_sample_content(base_path.join("content"), clobber)?;
+
+    let interactive =
+        !assume_yes && std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    let (theme, config) = if interactive {
+        wizard::ask(&project_name(base_path)?, theme, base_url)?
+    } else {
+        (theme, theme_config(theme, base_url)?)
+    };
+
+    write_file_checked(base_path.join("mllt.toml"), toml::to_string_pretty(&config)?, clobber)?;
+    create_sample_theme(base_path.join("theme"), theme, clobber)?;
+    create_sample_layouts(base_path.join("layouts"), clobber)?;
+    create_sample_content(base_path.join("content"), theme, clobber)?;
     create_sample_assets(base_path.join("assets"), clobber)?;
+    create_sample_data(base_path.join("data"), clobber)?;
+    write_file_checked(base_path.join(".gitignore"), GITIGNORE, clobber)?;
     Ok(())
 }
 
+/// Create a site by copying the template project at `source`, a
+/// local folder or a git URL, substituting its placeholders.
+pub fn instantiate_from(
+    base_path: impl AsRef<Path>,
+    clobber: bool,
+    source: &str,
+    base_url: Option<&str>,
+) -> Result<()> {
+    let base_path = base_path.as_ref();
+    check_base_url(base_url)?;
+    let placeholders = template::Placeholders {
+        project_name: project_name(base_path)?,
+        base_url: base_url
+            .map(str::to_owned)
+            .unwrap_or_else(|| Config::default().site.baseurl),
+    };
+    template::instantiate(source, base_path, clobber, &placeholders)
+}
+
+/// A `--base-url` is checked like the wizard's answer, before
+/// anything is created, so it never ends up in mllt.toml broken.
+fn check_base_url(base_url: Option<&str>) -> Result<()> {
+    match base_url {
+        Some(base_url) => wizard::check_base_url(base_url).map_err(|e| eyre!("{e}.")),
+        None => Ok(()),
+    }
+}
+
+/// The name of the folder the site is created in.
+fn project_name(base_path: &Path) -> Result<String> {
+    std::path::absolute(base_path)?
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or(eyre!("\"{}\" has no folder name.", base_path.display()))
+}
+
+/// Print the starter themes that `mllt new --theme` accepts.
+pub fn list_themes() {
+    for theme in themes::THEMES {
+        let default = if theme.name == themes::DEFAULT { " (default)" } else { "" };
+        println!("{:<10} {}{default}", theme.name, theme.description);
+    }
+}
+
 fn create_project_dir(project_dir: impl AsRef<Path>, clobber: bool) -> Result<()> {
     let project_dir = project_dir.as_ref();
     
@@ -41,36 +128,46 @@ fn create_project_dir(project_dir: impl AsRef<Path>, clobber: bool) -> Result<()
     Ok(())
 }
 
-fn write_serde_default<T: Default + Serialize>(
-    path: impl AsRef<Path>,
-    clobber: bool,
-) -> Result<()> {
-    let path = path.as_ref();
-
-    write_file_checked(path, toml::to_string_pretty(&T::default())?, clobber)?;
+/// The default config, with the `[params]` that `theme` uses.
+fn theme_config(theme: &StarterTheme, base_url: Option<&str>) -> Result<Config> {
+    let mut config = Config {
+        params: toml::from_str(theme.params)?,
+        ..Default::default()
+    };
+    if let Some(base_url) = base_url {
+        config.site.baseurl = base_url.to_owned();
+    }
 
-    Ok(())
+    Ok(config)
 }
 
-fn create_sample_theme(theme_dir: impl AsRef<Path>, clobber: bool) -> Result<()> {
+fn create_sample_theme(theme_dir: impl AsRef<Path>, theme: &StarterTheme, clobber: bool) -> Result<()> {
     let theme_dir = theme_dir.as_ref();
     
     create_dir_all_checked(theme_dir, clobber)?;
 
-    write_file_checked(theme_dir.join("style.hbs").as_path(), include_str!("./style.hbs"), clobber)?;
-    write_file_checked(theme_dir.join("header.hbs").as_path(), include_str!("./header.hbs"), clobber)?;
-    write_file_checked(theme_dir.join("head.hbs").as_path(), include_str!("./head.hbs"), clobber)?;
-    write_file_checked(theme_dir.join("footer.hbs").as_path(), include_str!("./footer.hbs"), clobber)?;
-    write_file_checked(theme_dir.join("page.hbs").as_path(), include_str!("./page.hbs"), clobber)?;
+    for (name, contents) in theme.files {
+        write_file_checked(theme_dir.join(name).as_path(), *contents, clobber)?;
+    }
 
     Ok(())
 }
 
-fn create_sample_content(content_dir: impl AsRef<Path>, clobber: bool) -> Result<()> {
+fn create_sample_layouts(layouts_dir: impl AsRef<Path>, clobber: bool) -> Result<()> {
+    let layouts_dir = layouts_dir.as_ref();
+
+    create_dir_all_checked(layouts_dir, clobber)?;
+
+    Ok(())
+}
+
+fn create_sample_content(content_dir: impl AsRef<Path>, theme: &StarterTheme, clobber: bool) -> Result<()> {
     let content_dir = content_dir.as_ref();
     create_dir_all_checked(content_dir, clobber)?;
 
-    write_file_checked(content_dir.join("index.hbs").as_path(), include_str!("./index.hbs"), clobber)?;
+    for (name, contents) in theme.content {
+        write_file_checked(content_dir.join(name).as_path(), *contents, clobber)?;
+    }
 
     Ok(())
 }
@@ -83,6 +180,14 @@ fn create_sample_assets(assets_dir: impl AsRef<Path>, clobber: bool) -> Result<(
     Ok(())
 }
 
+fn create_sample_data(data_dir: impl AsRef<Path>, clobber: bool) -> Result<()> {
+    let data_dir = data_dir.as_ref();
+
+    create_dir_all_checked(data_dir, clobber)?;
+
+    Ok(())
+}
+
 fn create_dir_all_checked(path: impl AsRef<Path>, clobber: bool) -> Result<()> {
     let path = path.as_ref();
 
@@ -120,7 +225,7 @@ fn create_dir_all_checked(path: impl AsRef<Path>, clobber: bool) -> Result<()> {
 
 fn write_file_checked(
     path: impl AsRef<Path>,
-    content: impl AsRef<str>,
+    content: impl AsRef<[u8]>,
     clobber: bool,
 ) -> Result<()> {
     let path = path.as_ref();
@@ -136,6 +241,6 @@ fn write_file_checked(
             bail!("File already exists: {}", path.display());
         }
     }
-    write!(File::create(path)?, "{}", content.as_ref())?;
+    File::create(path)?.write_all(content.as_ref())?;
     Ok(())
 }
@@ -0,0 +1,162 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Creating sites from a template project: any folder with an
+//! `mllt.toml` in it, on disk or in a git repository.
+
+use color_eyre::eyre::{bail, Context as _, Result};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tempfile::TempDir;
+use tracing::{debug, info, warn};
+use walkdir::WalkDir;
+
+use super::{create_dir_all_checked, write_file_checked};
+
+/// Replaced by the new project's folder name.
+pub const PROJECT_NAME: &str = "__PROJECT_NAME__";
+
+/// Replaced by the `--base-url` given to `mllt new`.
+pub const BASE_URL: &str = "__BASE_URL__";
+
+/// Values substituted for the placeholders in a template's text files.
+pub struct Placeholders {
+    pub project_name: String,
+    pub base_url: String,
+}
+
+impl Placeholders {
+    fn apply(&self, text: &str) -> String {
+        text.replace(PROJECT_NAME, &self.project_name)
+            .replace(BASE_URL, &self.base_url)
+    }
+}
+
+/// Copy the template at `source` into `base_path`. `source` is
+/// either a local folder or a git URL, which is cloned first.
+pub fn instantiate(
+    source: &str,
+    base_path: &Path,
+    clobber: bool,
+    placeholders: &Placeholders,
+) -> Result<()> {
+    if !is_git_url(source) {
+        return copy_template(Path::new(source), base_path, clobber, placeholders);
+    }
+
+    // Removed when dropped, however the copy goes.
+    let checkout = clone(source)?;
+    copy_template(checkout.path(), base_path, clobber, placeholders)
+}
+
+/// Whether `source` looks like something `git clone` accepts
+/// rather than a path on disk.
+fn is_git_url(source: &str) -> bool {
+    ["https://", "http://", "ssh://", "git://", "file://", "git@"]
+        .iter()
+        .any(|prefix| source.starts_with(prefix))
+        || (source.ends_with(".git") && !Path::new(source).is_dir())
+}
+
+/// Shallow-clone `url` into a temporary folder.
+fn clone(url: &str) -> Result<TempDir> {
+    let checkout = tempfile::Builder::new()
+        .prefix("mllt-template-")
+        .tempdir()?;
+
+    info!("Cloning template \"{url}\"...");
+    let status = Command::new("git")
+        .args(["clone", "--quiet", "--depth", "1", "--", url])
+        .arg(checkout.path())
+        .status()
+        .context("Error running git, is it installed?")?;
+    if !status.success() {
+        bail!("Error cloning template \"{url}\": git exited with {status}.");
+    }
+
+    Ok(checkout)
+}
+
+/// Copy every file under `template` to the same place under `dst`,
+/// substituting placeholders in the ones that are text.
+fn copy_template(
+    template: &Path,
+    dst: &Path,
+    clobber: bool,
+    placeholders: &Placeholders,
+) -> Result<()> {
+    if !template.join("mllt.toml").is_file() {
+        bail!(
+            "\"{}\" is not a site template: it has no mllt.toml.",
+            template.display()
+        );
+    }
+
+    // Otherwise the copy would end up copying itself.
+    if resolve(dst)?.starts_with(template.canonicalize()?) {
+        bail!(
+            "Can't create a site inside its own template \"{}\".",
+            template.display()
+        );
+    }
+
+    create_dir_all_checked(dst, clobber)?;
+    let entries = WalkDir::new(template)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git");
+    for entry in entries {
+        let entry = entry?;
+        let relative_path = entry.path().strip_prefix(template)?;
+        let target = dst.join(relative_path);
+
+        if entry.file_type().is_dir() {
+            create_dir_all_checked(&target, clobber)?;
+        } else if entry.file_type().is_file() {
+            let contents = std::fs::read(entry.path())
+                .context(format!("Error reading \"{}\"", entry.path().display()))?;
+            match String::from_utf8(contents) {
+                Ok(text) => write_file_checked(&target, placeholders.apply(&text), clobber)?,
+                Err(binary) => write_file_checked(&target, binary.into_bytes(), clobber)?,
+            }
+            debug!("Copied template file: {}", target.display());
+        } else if entry.file_type().is_symlink() {
+            warn!(
+                "Skipped \"{}\": templates can't contain symlinks.",
+                relative_path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// `path` made absolute with symlinks resolved, as far
+/// as it exists; the rest is appended unchanged.
+fn resolve(path: &Path) -> Result<PathBuf> {
+    let path = std::path::absolute(path)?;
+    for ancestor in path.ancestors() {
+        if let Ok(real) = ancestor.canonicalize() {
+            return Ok(real.join(path.strip_prefix(ancestor)?));
+        }
+    }
+    Ok(path)
+}
@@ -0,0 +1,100 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Starter themes built into mllt, one of which
+//! every new site is created with.
+
+/// A complete theme, with sample content and
+/// the `[params]` its templates expect.
+pub struct StarterTheme {
+    /// What `mllt new --theme` takes.
+    pub name: &'static str,
+    pub description: &'static str,
+
+    /// Files of the theme folder, by name.
+    pub files: &'static [(&'static str, &'static str)],
+
+    /// Content pages showing the theme off, by name.
+    pub content: &'static [(&'static str, &'static str)],
+
+    /// TOML for the site's `[params]` section.
+    pub params: &'static str,
+}
+
+/// Every file of a starter theme folder besides its sample content.
+/// They all wrap their pages in the same `page.hbs`.
+macro_rules! theme_files {
+    ($dir:literal) => {
+        &[
+            ("theme.toml", include_str!(concat!($dir, "/theme.toml"))),
+            ("head.hbs", include_str!(concat!($dir, "/head.hbs"))),
+            ("header.hbs", include_str!(concat!($dir, "/header.hbs"))),
+            ("footer.hbs", include_str!(concat!($dir, "/footer.hbs"))),
+            ("page.hbs", include_str!("page.hbs")),
+            ("style.hbs", include_str!(concat!($dir, "/style.hbs"))),
+        ]
+    };
+}
+
+pub static THEMES: &[StarterTheme] = &[
+    StarterTheme {
+        name: "minimal",
+        description: "A single centered column of links.",
+        files: theme_files!("minimal"),
+        content: &[("index.hbs", include_str!("minimal/index.hbs"))],
+        params: include_str!("minimal/params.toml"),
+    },
+    StarterTheme {
+        name: "cards",
+        description: "A grid of link cards under an avatar, on a warm background.",
+        files: theme_files!("cards"),
+        content: &[("index.hbs", include_str!("cards/index.hbs"))],
+        params: include_str!("cards/params.toml"),
+    },
+    StarterTheme {
+        name: "dark",
+        description: "Stacked buttons on a dark background, in an accent color of your choice.",
+        files: theme_files!("dark"),
+        content: &[("index.hbs", include_str!("dark/index.hbs"))],
+        params: include_str!("dark/params.toml"),
+    },
+    StarterTheme {
+        name: "terminal",
+        description: "Green-on-black shell session, complete with blinking cursor.",
+        files: theme_files!("terminal"),
+        content: &[("index.hbs", include_str!("terminal/index.hbs"))],
+        params: include_str!("terminal/params.toml"),
+    },
+];
+
+/// The theme new sites get unless told otherwise.
+pub const DEFAULT: &str = "minimal";
+
+/// Look a starter theme up by name.
+pub fn find(name: &str) -> Option<&'static StarterTheme> {
+    THEMES.iter().find(|t| t.name == name)
+}
+
+/// Names of every starter theme, for error messages.
+pub fn names() -> String {
+    THEMES
+        .iter()
+        .map(|t| format!("\"{}\"", t.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,220 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! The questions `mllt new` asks when it's run in a terminal.
+
+use color_eyre::eyre::Result;
+use dialoguer::{theme::ColorfulTheme, Input, Select};
+
+use super::themes::{self, StarterTheme};
+use crate::{
+    config::{Config, Link},
+    platforms,
+};
+
+/// Ask for the new site's title, description, base URL, starter theme
+/// and links. Whatever was given on the command line is the default.
+pub fn ask(
+    project_name: &str,
+    theme: &'static StarterTheme,
+    base_url: Option<&str>,
+) -> Result<(&'static StarterTheme, Config)> {
+    let prompts = ColorfulTheme::default();
+
+    let title: String = Input::with_theme(&prompts)
+        .with_prompt("Site title")
+        .default(project_name.to_owned())
+        .interact_text()?;
+    let desc: String = Input::with_theme(&prompts)
+        .with_prompt("Description")
+        .allow_empty(true)
+        .interact_text()?;
+    let base_url: String = Input::with_theme(&prompts)
+        .with_prompt("Base URL")
+        .default(
+            base_url
+                .map(str::to_owned)
+                .unwrap_or_else(|| Config::default().site.baseurl),
+        )
+        .validate_with(|answer: &String| check_base_url(answer))
+        .interact_text()?;
+
+    let choices: Vec<String> = themes::THEMES
+        .iter()
+        .map(|t| format!("{:<10} {}", t.name, t.description))
+        .collect();
+    let chosen = Select::with_theme(&prompts)
+        .with_prompt("Theme")
+        .items(&choices)
+        .default(
+            themes::THEMES
+                .iter()
+                .position(|t| t.name == theme.name)
+                .unwrap_or_default(),
+        )
+        .interact()?;
+    let theme = &themes::THEMES[chosen];
+
+    let mut config = super::theme_config(theme, Some(&base_url))?;
+    config.params.insert("title".into(), title.into());
+    config.params.insert("desc".into(), desc.into());
+    config.links = ask_links(&prompts)?;
+
+    Ok((theme, config))
+}
+
+/// Every `absURL` and `relURL` starts with the base URL,
+/// so one that isn't a usable URL would break them all.
+pub(super) fn check_base_url(answer: &str) -> Result<(), String> {
+    crate::url::check(answer).map_err(|e| format!("The base URL {e}"))
+}
+
+/// Links are entered one at a time until an empty answer.
+fn ask_links(prompts: &ColorfulTheme) -> Result<Vec<Link>> {
+    let mut links = Vec::new();
+    loop {
+        let answer: String = Input::with_theme(prompts)
+            .with_prompt(format!(
+                "Link #{} (platform:handle or URL, empty when done)",
+                links.len() + 1
+            ))
+            .allow_empty(true)
+            .validate_with(|answer: &String| parse_link(answer).map(|_| ()))
+            .interact_text()?;
+        let Ok(Some(mut link)) = parse_link(&answer) else {
+            break;
+        };
+
+        if let Some(url) = link.url.clone() {
+            let name: String = Input::with_theme(prompts)
+                .with_prompt(format!("Name for {url}"))
+                .default(url)
+                .interact_text()?;
+            link.name = Some(name);
+        }
+        links.push(link);
+    }
+
+    Ok(links)
+}
+
+/// `github:you` is a link to a built-in platform,
+/// anything with a scheme is a plain URL.
+fn parse_link(answer: &str) -> Result<Option<Link>, String> {
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Ok(None);
+    }
+
+    if let Some((id, handle)) = answer.split_once(':') {
+        if let Some(platform) = platforms::find(id.trim()) {
+            let handle = handle.trim();
+            platform.url(handle)?;
+            return Ok(Some(Link::on(platform.id, handle)));
+        }
+    }
+
+    if answer.contains("://") || answer.starts_with("mailto:") {
+        // Checked like the config file's links, so that the
+        // mllt.toml written out is sure to load again.
+        let link = serde_json::json!({ "name": answer, "url": answer });
+        return serde_json::from_value(link)
+            .map(Some)
+            .map_err(|e| format!("Invalid URL: {e}"));
+    }
+
+    Err(format!(
+        "Expected a URL or platform:handle, where the platform is one of {}",
+        platforms::ids()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(platform, handle, url)` of the link parsed from `answer`.
+    fn parse(answer: &str) -> (Option<String>, Option<String>, Option<String>) {
+        let link = parse_link(answer).unwrap().unwrap();
+        (link.platform, link.handle, link.url)
+    }
+
+    fn platform(id: &str, handle: &str) -> (Option<String>, Option<String>, Option<String>) {
+        (Some(id.to_owned()), Some(handle.to_owned()), None)
+    }
+
+    #[test]
+    fn platform_handles() {
+        assert_eq!(parse("github:you"), platform("github", "you"));
+        assert_eq!(parse("  github : you  "), platform("github", "you"));
+        assert_eq!(
+            parse("mastodon:@you@mastodon.social"),
+            platform("mastodon", "@you@mastodon.social")
+        );
+        assert_eq!(
+            parse("matrix:@you:matrix.org"),
+            platform("matrix", "@you:matrix.org")
+        );
+        assert_eq!(
+            parse("rss:https://blog.example.com/feed.xml"),
+            platform("rss", "https://blog.example.com/feed.xml")
+        );
+    }
+
+    #[test]
+    fn urls() {
+        let url = "https://blog.example.com";
+        assert_eq!(parse(url), (None, None, Some(url.to_owned())));
+        let mail = "mailto:you@example.com";
+        assert_eq!(parse(mail), (None, None, Some(mail.to_owned())));
+    }
+
+    #[test]
+    fn blank_answer_ends_the_list() {
+        assert!(parse_link("").unwrap().is_none());
+        assert!(parse_link("   ").unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_answers() {
+        for answer in [
+            "github:",
+            "github:  ",
+            "github:you and me",
+            "mastodon:you",
+            "email:you",
+            "myspace:you",
+            "example.com",
+            "https://",
+            "https:///about",
+            "https://example.com/a b",
+        ] {
+            assert!(parse_link(answer).is_err(), "{answer}");
+        }
+    }
+
+    #[test]
+    fn base_urls() {
+        for answer in ["/", "/blog/", "https://example.com", "https://example.com/blog/"] {
+            assert!(check_base_url(answer).is_ok(), "{answer}");
+        }
+        for answer in ["", " ", "/my blog/", "https://", "https:///blog"] {
+            assert!(check_base_url(answer).is_err(), "{answer}");
+        }
+    }
+}
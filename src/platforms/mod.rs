@@ -0,0 +1,272 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Well-known platforms that a link can point at with just a handle,
+//! e.g. `{ platform = "github", handle = "you" }`.
+
+/// A platform in the built-in registry.
+pub struct Platform {
+    /// What links put in their `platform` field.
+    pub id: &'static str,
+
+    /// Display name, used when a link doesn't have one.
+    pub name: &'static str,
+
+    /// Inline SVG icon, drawn with `currentColor`.
+    pub icon: &'static str,
+
+    /// How a handle turns into the link's URL.
+    url: Url,
+}
+
+enum Url {
+    /// The handle goes after this prefix. Handles on most
+    /// platforms are written with or without a leading `@`.
+    Profile(&'static str),
+    /// Turns a handle into the URL, or explains why the
+    /// handle isn't valid for this platform.
+    Custom(fn(&str) -> Result<String, String>),
+}
+
+impl Platform {
+    pub fn url(&self, handle: &str) -> Result<String, String> {
+        let handle = handle.trim();
+        if handle.is_empty() || handle.contains(char::is_whitespace) {
+            return Err(format!("\"{handle}\" is not a valid {} handle", self.name));
+        }
+        match self.url {
+            Url::Profile(prefix) => Ok(format!("{prefix}{}", handle.trim_start_matches('@'))),
+            Url::Custom(url) => url(handle),
+        }
+    }
+}
+
+/// Look a platform up by its id.
+pub fn find(id: &str) -> Option<&'static Platform> {
+    PLATFORMS.iter().find(|p| p.id == id)
+}
+
+/// Ids of every known platform, for error messages.
+pub fn ids() -> String {
+    PLATFORMS
+        .iter()
+        .map(|p| format!("`{}`", p.id))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Platforms whose handle is a URL take ones that links could have as
+/// their `url`, as long as they lead somewhere from any page.
+fn web_url(handle: &str) -> Result<String, String> {
+    crate::url::check(handle).map_err(|e| format!("\"{handle}\" is not a valid URL: it {e}"))?;
+    if !crate::url::is_absolute(handle) && !handle.starts_with('/') {
+        return Err(format!(
+            "\"{handle}\" is not a URL, did you mean \"https://{handle}\"?"
+        ));
+    }
+    Ok(handle.to_owned())
+}
+
+pub static PLATFORMS: &[Platform] = &[
+    Platform {
+        id: "github",
+        name: "GitHub",
+        icon: include_str!("icons/github.svg"),
+        url: Url::Profile("https://github.com/"),
+    },
+    Platform {
+        id: "gitlab",
+        name: "GitLab",
+        icon: include_str!("icons/gitlab.svg"),
+        url: Url::Profile("https://gitlab.com/"),
+    },
+    Platform {
+        id: "codeberg",
+        name: "Codeberg",
+        icon: include_str!("icons/codeberg.svg"),
+        url: Url::Profile("https://codeberg.org/"),
+    },
+    Platform {
+        id: "mastodon",
+        name: "Mastodon",
+        icon: include_str!("icons/mastodon.svg"),
+        url: Url::Custom(|h| match h.trim_start_matches('@').split_once('@') {
+            Some((name, instance)) if !name.is_empty() && !instance.is_empty() => {
+                Ok(format!("https://{instance}/@{name}"))
+            }
+            _ => Err(format!(
+                "Mastodon handles need an instance, like \"@you@mastodon.social\", got \"{h}\""
+            )),
+        }),
+    },
+    Platform {
+        id: "bluesky",
+        name: "Bluesky",
+        icon: include_str!("icons/bluesky.svg"),
+        url: Url::Profile("https://bsky.app/profile/"),
+    },
+    Platform {
+        id: "youtube",
+        name: "YouTube",
+        icon: include_str!("icons/youtube.svg"),
+        url: Url::Profile("https://www.youtube.com/@"),
+    },
+    Platform {
+        id: "twitch",
+        name: "Twitch",
+        icon: include_str!("icons/twitch.svg"),
+        url: Url::Profile("https://www.twitch.tv/"),
+    },
+    Platform {
+        id: "x",
+        name: "X",
+        icon: include_str!("icons/x.svg"),
+        url: Url::Profile("https://x.com/"),
+    },
+    Platform {
+        id: "instagram",
+        name: "Instagram",
+        icon: include_str!("icons/instagram.svg"),
+        url: Url::Profile("https://www.instagram.com/"),
+    },
+    Platform {
+        id: "linkedin",
+        name: "LinkedIn",
+        icon: include_str!("icons/linkedin.svg"),
+        url: Url::Profile("https://www.linkedin.com/in/"),
+    },
+    Platform {
+        id: "kofi",
+        name: "Ko-fi",
+        icon: include_str!("icons/kofi.svg"),
+        url: Url::Profile("https://ko-fi.com/"),
+    },
+    Platform {
+        id: "patreon",
+        name: "Patreon",
+        icon: include_str!("icons/patreon.svg"),
+        url: Url::Profile("https://www.patreon.com/"),
+    },
+    Platform {
+        id: "matrix",
+        name: "Matrix",
+        icon: include_str!("icons/matrix.svg"),
+        url: Url::Custom(|h| match h.trim_start_matches('@').split_once(':') {
+            Some((name, server)) if !name.is_empty() && !server.is_empty() => {
+                Ok(format!("https://matrix.to/#/@{name}:{server}"))
+            }
+            _ => Err(format!(
+                "Matrix handles need a server, like \"@you:matrix.org\", got \"{h}\""
+            )),
+        }),
+    },
+    Platform {
+        id: "email",
+        name: "Email",
+        icon: include_str!("icons/email.svg"),
+        url: Url::Custom(|h| match h.split_once('@') {
+            Some((name, domain)) if !name.is_empty() && !domain.is_empty() => {
+                Ok(format!("mailto:{h}"))
+            }
+            _ => Err(format!("\"{h}\" is not an email address")),
+        }),
+    },
+    Platform {
+        id: "rss",
+        name: "RSS",
+        icon: include_str!("icons/rss.svg"),
+        // The handle is the feed's URL.
+        url: Url::Custom(web_url),
+    },
+    Platform {
+        id: "website",
+        name: "Website",
+        icon: include_str!("icons/website.svg"),
+        url: Url::Custom(web_url),
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(id: &str, handle: &str) -> Result<String, String> {
+        find(id).unwrap().url(handle)
+    }
+
+    #[test]
+    fn profiles() {
+        assert_eq!(url("github", "you").unwrap(), "https://github.com/you");
+        assert_eq!(url("github", " @you ").unwrap(), "https://github.com/you");
+        assert_eq!(
+            url("youtube", "@you").unwrap(),
+            "https://www.youtube.com/@you"
+        );
+        assert!(url("github", "").is_err());
+        assert!(url("github", "you and me").is_err());
+    }
+
+    #[test]
+    fn mastodon() {
+        let expected = "https://mastodon.social/@you";
+        assert_eq!(url("mastodon", "@you@mastodon.social").unwrap(), expected);
+        assert_eq!(url("mastodon", "you@mastodon.social").unwrap(), expected);
+        assert!(url("mastodon", "@you").is_err());
+        assert!(url("mastodon", "@you@").is_err());
+        assert!(url("mastodon", "@@mastodon.social").is_err());
+    }
+
+    #[test]
+    fn matrix() {
+        let expected = "https://matrix.to/#/@you:matrix.org";
+        assert_eq!(url("matrix", "@you:matrix.org").unwrap(), expected);
+        assert_eq!(url("matrix", "you:matrix.org").unwrap(), expected);
+        assert!(url("matrix", "@you").is_err());
+        assert!(url("matrix", "@you:").is_err());
+    }
+
+    #[test]
+    fn email() {
+        assert_eq!(
+            url("email", "you@example.com").unwrap(),
+            "mailto:you@example.com"
+        );
+        assert!(url("email", "you").is_err());
+        assert!(url("email", "@example.com").is_err());
+        assert!(url("email", "you@").is_err());
+    }
+
+    #[test]
+    fn urls_as_handles() {
+        let feed = "https://blog.example.com/feed.xml";
+        assert_eq!(url("rss", feed).unwrap(), feed);
+        assert_eq!(url("rss", "/feed.xml").unwrap(), "/feed.xml");
+        assert_eq!(url("website", "https://me.dev").unwrap(), "https://me.dev");
+        for handle in ["not a url", "example.com", "https://", "https:///feed.xml"] {
+            assert!(url("rss", handle).is_err(), "{handle}");
+            assert!(url("website", handle).is_err(), "{handle}");
+        }
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        for (i, platform) in PLATFORMS.iter().enumerate() {
+            assert!(PLATFORMS[..i].iter().all(|p| p.id != platform.id));
+        }
+    }
+}
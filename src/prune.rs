@@ -0,0 +1,232 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use color_eyre::eyre::{bail, Context as _, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use hashbrown::HashSet;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+use walkdir::WalkDir;
+
+/// Delete every file in `out_dir` that isn't in `produced` (paths
+/// relative to `out_dir`) and doesn't match one of the `protect`
+/// globs, then remove any directories this leaves empty. With
+/// `dry_run`, only log what would be removed.
+///
+/// Returns the paths, relative to `out_dir`, of the removed files.
+pub fn prune(
+    out_dir: impl AsRef<Path>,
+    produced: &HashSet<PathBuf>,
+    protect: &[String],
+    dry_run: bool,
+) -> Result<Vec<PathBuf>> {
+    let out_dir = out_dir.as_ref();
+    let protect = build_globset(protect)?;
+
+    let mut stale = Vec::new();
+    for entry in WalkDir::new(out_dir).min_depth(1) {
+        let entry = entry?;
+        let relative_path = entry.path().strip_prefix(out_dir)?;
+
+        if !entry.file_type().is_dir()
+            && !produced.contains(relative_path)
+            && !protect.is_match(relative_path)
+        {
+            stale.push(relative_path.to_owned());
+        }
+    }
+    stale.sort();
+
+    for path in &stale {
+        if dry_run {
+            info!("Would remove: {}", path.display());
+        } else {
+            std::fs::remove_file(out_dir.join(path))?;
+            debug!("Removed: {}", path.display());
+        }
+    }
+
+    if !dry_run {
+        remove_empty_dirs(out_dir)?;
+    }
+
+    Ok(stale)
+}
+
+/// Make sure pruning `out_dir` can't delete the site itself: it mustn't
+/// be or contain the project `root`, nor overlap any of the `sources`.
+pub fn check_out_dir(out_dir: impl AsRef<Path>, root: &Path, sources: &[PathBuf]) -> Result<()> {
+    let out_dir = out_dir.as_ref();
+    // Nothing there yet means nothing to delete.
+    let Ok(out) = out_dir.canonicalize() else {
+        return Ok(());
+    };
+
+    if root.canonicalize().is_ok_and(|root| root.starts_with(&out)) {
+        bail!(
+            "Refusing to prune \"{}\", which holds the whole project. Set `publishdir` to a folder of its own.",
+            out_dir.display()
+        );
+    }
+
+    for source in sources {
+        let Ok(src) = source.canonicalize() else {
+            continue;
+        };
+        if src.starts_with(&out) || out.starts_with(&src) {
+            bail!(
+                "Refusing to prune \"{}\", which overlaps the site's source folder \"{}\". Set `publishdir` to a folder of its own.",
+                out_dir.display(),
+                source.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Compile the `protect` patterns. A trailing slash protects a
+/// whole directory, e.g. `.well-known/` means `.well-known/**`.
+fn build_globset(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = match pattern.strip_suffix('/') {
+            Some(dir) => format!("{dir}/**"),
+            None => pattern.clone(),
+        };
+
+        builder.add(
+            GlobBuilder::new(&pattern)
+                .literal_separator(true)
+                .build()
+                .context(format!("Invalid protect pattern: \"{pattern}\""))?,
+        );
+    }
+
+    Ok(builder.build()?)
+}
+
+fn remove_empty_dirs(out_dir: &Path) -> Result<()> {
+    // Children come before their parents, so directories that
+    // only contained empty directories get cleaned up too.
+    for entry in WalkDir::new(out_dir).min_depth(1).contents_first(true) {
+        let entry = entry?;
+        if entry.file_type().is_dir() && std::fs::read_dir(entry.path())?.next().is_none() {
+            std::fs::remove_dir(entry.path())?;
+            debug!("Removed empty directory: {}", entry.path().display());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn patterns(patterns: &[&str]) -> GlobSet {
+        build_globset(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn trailing_slash_protects_a_directory() {
+        let protect = patterns(&[".well-known/"]);
+        assert!(protect.is_match(".well-known/security.txt"));
+        assert!(protect.is_match(".well-known/acme/token"));
+        assert!(!protect.is_match(".well-known-not"));
+        assert!(!protect.is_match("docs/.well-known/security.txt"));
+    }
+
+    #[test]
+    fn wildcards_stay_in_their_directory() {
+        let protect = patterns(&["CNAME", "*.txt"]);
+        assert!(protect.is_match("CNAME"));
+        assert!(protect.is_match("robots.txt"));
+        assert!(!protect.is_match("notes/todo.txt"));
+        assert!(!protect.is_match("docs/CNAME"));
+    }
+
+    #[test]
+    fn invalid_pattern_is_an_error() {
+        assert!(build_globset(&["[".to_owned()]).is_err());
+    }
+
+    /// An output folder holding `files`, all with the same contents.
+    fn out_dir(files: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in files {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        dir
+    }
+
+    fn produced(paths: &[&str]) -> HashSet<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn removes_stale_files_and_empty_dirs() {
+        let dir = out_dir(&["index.html", "old.html", "old/page.html", "CNAME"]);
+        let removed = prune(
+            dir.path(),
+            &produced(&["index.html"]),
+            &["CNAME".to_owned()],
+            false,
+        )
+        .unwrap();
+
+        // Sorted by path component, so `old/` comes before `old.html`.
+        assert_eq!(
+            removed,
+            [PathBuf::from("old/page.html"), PathBuf::from("old.html")]
+        );
+        assert!(dir.path().join("index.html").is_file());
+        assert!(dir.path().join("CNAME").is_file());
+        assert!(!dir.path().join("old").exists());
+    }
+
+    #[test]
+    fn dry_run_removes_nothing() {
+        let dir = out_dir(&["index.html", "old.html"]);
+        let removed = prune(dir.path(), &produced(&["index.html"]), &[], true).unwrap();
+
+        assert_eq!(removed, [PathBuf::from("old.html")]);
+        assert!(dir.path().join("old.html").is_file());
+    }
+
+    #[test]
+    fn out_dir_must_be_apart_from_the_sources() {
+        let root = out_dir(&["mllt.toml", "content/index.hbs", "output/index.html"]);
+        let root = root.path();
+        let sources = [root.join("content"), root.join("assets")];
+
+        assert!(check_out_dir(root.join("output"), root, &sources).is_ok());
+        assert!(check_out_dir(root.join("missing"), root, &sources).is_ok());
+        assert!(check_out_dir(root, root, &sources).is_err());
+        assert!(check_out_dir(root.join("content"), root, &sources).is_err());
+        assert!(check_out_dir(root.join("content/index.hbs"), root, &sources).is_err());
+
+        fs::create_dir(root.join("content/output")).unwrap();
+        assert!(check_out_dir(root.join("content/output"), root, &sources).is_err());
+        // Parent of the project root.
+        assert!(check_out_dir(root.parent().unwrap(), root, &sources).is_err());
+    }
+}
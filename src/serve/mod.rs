@@ -0,0 +1,561 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use color_eyre::eyre::{eyre, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use percent_encoding::percent_decode_str;
+use std::{
+    fs::File,
+    io::Write,
+    path::{Component, Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tempfile::TempDir;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    cli::{Cli, SiteArgs},
+    config::Config,
+    fingerprint::Fingerprint,
+    site::Site,
+    theme,
+};
+
+/// Endpoint the injected live reload script listens on.
+const LIVERELOAD_PATH: &str = "/__mllt/livereload";
+
+/// Build the site described by `args` and serve its output directory
+/// over HTTP on localhost, rebuilding and reloading open browser tabs
+/// whenever the site's sources change. Runs until the process is killed.
+pub fn serve(args: &SiteArgs, cli: &Cli, port: u16) -> Result<()> {
+    let config_path = args.config.as_path();
+
+    // Never clobber the production output folder with a development
+    // build, unless explicitly asked to.
+    let (out_dir, _temp_dir) = match &args.output {
+        Some(out_dir) => (out_dir.clone(), None),
+        None => {
+            let temp_dir = temp_out_dir()?;
+            (temp_dir.path().to_owned(), Some(temp_dir))
+        }
+    };
+    let load_config = || -> Result<Config> {
+        let mut config = Config::from_file(config_path)?.merge_with(cli);
+        config.site.out_dir = out_dir.clone();
+        // Links have to point at the preview, not the live site.
+        if args.base_url.is_none() {
+            config.site.baseurl = format!("http://localhost:{port}/");
+        }
+        Ok(config)
+    };
+
+    // Refuse to start at all if the initial config is broken.
+    let mut config = Some(load_config()?);
+
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| eyre!("Unable to bind to port {port}: {e}"))?;
+    let clients = ReloadClients::default();
+    {
+        let out_dir = out_dir.clone();
+        let clients = clients.clone();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let root = out_dir.clone();
+                let clients = clients.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = respond(request, &root, &clients) {
+                        warn!("Error responding to request: {e}");
+                    }
+                });
+            }
+        });
+    }
+
+    let config_file = config_path.canonicalize()?;
+    loop {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+
+        // Editors often replace files instead of writing to them,
+        // so watch the directory holding the config file instead.
+        if let Some(parent) = config_file.parent() {
+            watcher.watch(parent, RecursiveMode::NonRecursive)?;
+        }
+
+        let config = match config.take().map_or_else(load_config, Ok) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Unable to load config: {e:?}");
+                while !next_changes(&rx)?.contains(&config_file) {}
+                continue;
+            }
+        };
+
+        let roots = WatchedRoots::new(&config);
+        for root in roots.iter() {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+        }
+        // Like the config file, by way of the directory holding it.
+        if let Some(favicon) = &roots.favicon {
+            if !roots.iter().any(|root| favicon.starts_with(root)) {
+                if let Some(parent) = favicon.parent() {
+                    watcher.watch(parent, RecursiveMode::NonRecursive)?;
+                }
+            }
+        }
+
+        info!("Building site to \"{}\"", config.site.out_dir.display());
+        let mut site = match Site::new(&config) {
+            Ok(site) => site,
+            Err(e) => {
+                error!("{e:?}");
+                loop {
+                    let changes = next_changes(&rx)?;
+                    if changes.contains(&config_file) || changes.iter().any(|p| roots.is_context(p)) {
+                        break;
+                    }
+                }
+                continue;
+            }
+        };
+        report(site.reload_templates().and_then(|_| site.render()));
+        info!("Serving site at http://localhost:{port}/ (Ctrl+C to stop)");
+        clients.reload();
+
+        loop {
+            let changes = next_changes(&rx)?;
+            if changes.contains(&config_file) {
+                info!("Config file changed, rebuilding site...");
+                break;
+            }
+            if changes.iter().any(|p| roots.is_context(p)) {
+                info!("Data files, icons, assets or theme.toml changed, rebuilding site...");
+                break;
+            }
+
+            let templates_changed = changes.iter().any(|p| roots.is_template(p));
+            let assets_changed = changes.iter().any(|p| roots.is_asset(p));
+
+            // Unless they're hashed, assets aren't part of the context,
+            // but pages linking to them with an integrity hash still
+            // need re-rendering, by a registry with fresh digests.
+            if templates_changed || (assets_changed && site.uses_assets()) {
+                report(site.reload_templates().and_then(|_| site.render_pages()));
+            }
+            if assets_changed {
+                report(site.copy_assets());
+            }
+            if templates_changed || assets_changed {
+                clients.reload();
+            }
+        }
+    }
+}
+
+/// The directory a site is rendered into by `mllt serve`. It's removed
+/// when dropped, and also on Ctrl+C, which would otherwise end the
+/// process without running any destructors.
+fn temp_out_dir() -> Result<TempDir> {
+    let temp_dir = tempfile::Builder::new().prefix("mllt-serve-").tempdir()?;
+    let path = temp_dir.path().to_owned();
+    ctrlc::set_handler(move || {
+        if let Err(e) = std::fs::remove_dir_all(&path) {
+            warn!("Couldn't remove \"{}\": {e}", path.display());
+        }
+        std::process::exit(130);
+    })?;
+    Ok(temp_dir)
+}
+
+/// Build errors shouldn't take the server down; the
+/// user is expected to fix them and save again.
+fn report<T>(result: Result<T>) {
+    if let Err(e) = result {
+        error!("{e:?}");
+    }
+}
+
+/// Block until the watcher reports at least one change, then
+/// wait for things to settle so that a burst of writes (e.g. an
+/// editor saving several files) only triggers a single rebuild.
+/// Returns the paths that changed.
+fn next_changes(rx: &Receiver<notify::Result<Event>>) -> Result<Vec<PathBuf>> {
+    fn collect(changes: &mut Vec<PathBuf>, event: notify::Result<Event>) {
+        match event {
+            // Rendering reads every source file, so listening
+            // to accesses would rebuild forever.
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                for path in &event.paths {
+                    debug!("Changed: {}", path.display());
+                }
+                changes.extend(event.paths);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("File watcher error: {e}"),
+        }
+    }
+
+    let mut changes = Vec::new();
+    loop {
+        collect(&mut changes, rx.recv()?);
+        loop {
+            match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(event) => collect(&mut changes, event),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Err(eyre!("File watcher stopped")),
+            }
+        }
+
+        if !changes.is_empty() {
+            return Ok(changes);
+        }
+    }
+}
+
+/// The canonicalized source folders of a site, used
+/// to decide what needs rebuilding after a change.
+struct WatchedRoots {
+    templates: Vec<PathBuf>,
+    assets: Option<PathBuf>,
+    data: Option<PathBuf>,
+    /// Theme icon overrides, which live inside the theme
+    /// folders but end up in the context like data files.
+    icons: Vec<PathBuf>,
+    /// The image the favicon set is generated from, which
+    /// doesn't have to be among the assets.
+    favicon: Option<PathBuf>,
+    /// Whether pages depend on the assets' contents, through
+    /// fingerprinted URLs or processed images' sizes.
+    hashed_assets: bool,
+}
+
+impl WatchedRoots {
+    fn new(config: &Config) -> Self {
+        // A broken theme chain is reported by the build, and then
+        // at least the theme itself is watched for a fix.
+        let themes: Vec<PathBuf> = match config.themes() {
+            Ok(themes) => themes.into_iter().map(|t| t.dir).collect(),
+            Err(_) => config.site.theme.iter().cloned().collect(),
+        };
+        let themes: Vec<PathBuf> = themes
+            .iter()
+            .filter_map(|p| p.canonicalize().ok())
+            .collect();
+
+        let templates = std::iter::once(&config.site.content)
+            .chain(config.site.layouts.as_ref())
+            .filter_map(|p| p.canonicalize().ok())
+            .chain(themes.iter().cloned())
+            .collect();
+
+        Self {
+            templates,
+            assets: config.site.assets.as_ref().and_then(|p| p.canonicalize().ok()),
+            data: config
+                .site
+                .data
+                .as_ref()
+                .and_then(|p| p.canonicalize().ok()),
+            icons: themes.iter().map(|p| p.join("icons")).collect(),
+            favicon: config.site.favicon.as_ref().and_then(|p| p.canonicalize().ok()),
+            hashed_assets: config.site.fingerprint != Fingerprint::Off || config.images.is_some(),
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &PathBuf> {
+        self.templates
+            .iter()
+            .chain(self.assets.as_ref())
+            .chain(self.data.as_ref())
+    }
+
+    fn is_template(&self, path: &Path) -> bool {
+        self.templates.iter().any(|root| path.starts_with(root))
+    }
+
+    fn is_asset(&self, path: &Path) -> bool {
+        self.assets.as_ref().is_some_and(|root| path.starts_with(root))
+            || self.favicon.as_deref() == Some(path)
+    }
+
+    /// Whether `path` feeds into the template context,
+    /// which is only built along with the whole site.
+    fn is_context(&self, path: &Path) -> bool {
+        // Can change which folders are watched.
+        if path.file_name().is_some_and(|name| name == theme::MANIFEST) {
+            return true;
+        }
+
+        if self.hashed_assets && self.is_asset(path) {
+            return true;
+        }
+
+        self.data
+            .iter()
+            .chain(&self.icons)
+            .any(|root| path.starts_with(root))
+    }
+}
+
+/// Open live reload connections, one per browser tab.
+#[derive(Clone, Default)]
+struct ReloadClients(Arc<Mutex<Vec<Sender<()>>>>);
+
+impl ReloadClients {
+    fn subscribe(&self) -> Receiver<()> {
+        let (tx, rx) = mpsc::channel();
+        self.0.lock().expect("reload client list poisoned").push(tx);
+        rx
+    }
+
+    /// Tell every connected tab to reload, forgetting
+    /// about the ones that have gone away.
+    fn reload(&self) {
+        self.0
+            .lock()
+            .expect("reload client list poisoned")
+            .retain(|tx| tx.send(()).is_ok());
+    }
+}
+
+fn respond(request: Request, root: &Path, clients: &ReloadClients) -> Result<()> {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        return Ok(request.respond(Response::empty(StatusCode(405)))?);
+    }
+
+    // Drop the query string and fragment, if any.
+    let url_path = request
+        .url()
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("/")
+        .to_owned();
+
+    if url_path == LIVERELOAD_PATH {
+        return stream_reload_events(request, clients);
+    }
+
+    let path = match resolve(root, &url_path) {
+        Target::File(path) => path,
+        Target::Redirect(location) => {
+            let header = Header::from_bytes("Location", location.as_bytes())
+                .map_err(|_| eyre!("Invalid redirect location {location}"))?;
+            return Ok(request.respond(Response::empty(StatusCode(301)).with_header(header))?);
+        }
+        Target::Missing => {
+            debug!("404: {url_path}");
+            return not_found(request, root, &url_path);
+        }
+    };
+
+    debug!("200: {url_path} -> {}", path.display());
+    if path.extension().and_then(|s| s.to_str()) == Some("html") {
+        let page = inject_livereload(&std::fs::read_to_string(&path)?);
+        return Ok(request.respond(Response::from_string(page).with_header(content_type(&path)))?);
+    }
+
+    let response = Response::from_file(File::open(&path)?).with_header(content_type(&path));
+    Ok(request.respond(response)?)
+}
+
+/// What a request path refers to in the output directory.
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    File(PathBuf),
+    /// A directory requested without its trailing slash.
+    Redirect(String),
+    Missing,
+}
+
+fn resolve(root: &Path, url_path: &str) -> Target {
+    let Some(relative) = sanitize_path(url_path) else {
+        debug!("Rejected path: {url_path}");
+        return Target::Missing;
+    };
+
+    let mut path = root.join(relative);
+    if path.is_dir() {
+        // Directories need a trailing slash so that relative
+        // links inside their index.html resolve correctly.
+        if !url_path.ends_with('/') {
+            return Target::Redirect(format!("{url_path}/"));
+        }
+        path = path.join("index.html");
+    } else if !path.exists() && path.extension().is_none() {
+        // Allow extensionless links to rendered pages, e.g. "/about".
+        path = path.with_extension("html");
+    }
+
+    match path.is_file() {
+        true => Target::File(path),
+        false => Target::Missing,
+    }
+}
+
+/// Hold the connection open as a server-sent event stream,
+/// emitting a `reload` event after every rebuild.
+fn stream_reload_events(request: Request, clients: &ReloadClients) -> Result<()> {
+    let rx = clients.subscribe();
+    let mut writer = request.into_writer();
+
+    writer.write_all(
+        b"HTTP/1.1 200 OK\r\n\
+        Content-Type: text/event-stream\r\n\
+        Cache-Control: no-cache\r\n\
+        Connection: keep-alive\r\n\r\n",
+    )?;
+    writer.flush()?;
+
+    loop {
+        let message: &[u8] = match rx.recv_timeout(Duration::from_secs(15)) {
+            Ok(()) => b"event: reload\ndata: {}\n\n",
+            // Periodically ping so disconnected tabs are noticed.
+            Err(RecvTimeoutError::Timeout) => b": keep-alive\n\n",
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+
+        if writer.write_all(message).and_then(|_| writer.flush()).is_err() {
+            debug!("Live reload client disconnected.");
+            return Ok(());
+        }
+    }
+}
+
+/// Insert the live reload script at the end of the page's body.
+fn inject_livereload(page: &str) -> String {
+    let script = include_str!("./livereload.html");
+    match page.rfind("</body>") {
+        Some(idx) => format!("{}{script}{}", &page[..idx], &page[idx..]),
+        None => format!("{page}{script}"),
+    }
+}
+
+/// Serve the site's own `404.html` if it rendered one,
+/// falling back to a built-in error page.
+fn not_found(request: Request, root: &Path, url_path: &str) -> Result<()> {
+    let custom = root.join("404.html");
+    let page = if custom.is_file() {
+        std::fs::read_to_string(custom)?
+    } else {
+        include_str!("./404.html").replace("{path}", &handlebars::html_escape(url_path))
+    };
+    let response = Response::from_string(inject_livereload(&page));
+
+    Ok(request.respond(
+        response
+            .with_status_code(404)
+            .with_header(content_type(Path::new("404.html"))),
+    )?)
+}
+
+/// Decode a request path into a path relative to the output
+/// directory, refusing anything that could escape it.
+fn sanitize_path(url_path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode_str(url_path).decode_utf8().ok()?;
+
+    let mut out = PathBuf::new();
+    for component in Path::new(decoded.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    Some(out)
+}
+
+fn content_type(path: &Path) -> Header {
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    let value = if mime.type_() == mime_guess::mime::TEXT
+        || mime.subtype() == mime_guess::mime::JAVASCRIPT
+        || mime.subtype() == mime_guess::mime::JSON
+    {
+        format!("{mime}; charset=utf-8")
+    } else {
+        mime.to_string()
+    };
+
+    Header::from_bytes("Content-Type", value.as_bytes()).expect("MIME types are valid header values")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_stay_inside_the_output() {
+        assert_eq!(sanitize_path("/"), Some(PathBuf::new()));
+        assert_eq!(sanitize_path("/a/./b.html"), Some(PathBuf::from("a/b.html")));
+        assert_eq!(sanitize_path("/my%20page.html"), Some(PathBuf::from("my page.html")));
+        for path in [
+            "/../secret",
+            "/a/../../secret",
+            "/%2e%2e/secret",
+            "/a/%2E%2E/%2e%2e/secret",
+            "/..%2fsecret",
+            "//etc/passwd/..",
+            "/%ff",
+        ] {
+            assert_eq!(sanitize_path(path), None, "{path}");
+        }
+    }
+
+    #[test]
+    fn directories_serve_their_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("blog")).unwrap();
+        for file in ["index.html", "about.html", "blog/index.html", "style.css"] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+
+        assert_eq!(resolve(root, "/"), Target::File(root.join("index.html")));
+        assert_eq!(resolve(root, "/blog/"), Target::File(root.join("blog/index.html")));
+        assert_eq!(resolve(root, "/blog"), Target::Redirect("/blog/".to_owned()));
+        assert_eq!(resolve(root, "/about"), Target::File(root.join("about.html")));
+        assert_eq!(resolve(root, "/style.css"), Target::File(root.join("style.css")));
+        assert_eq!(resolve(root, "/missing"), Target::Missing);
+        assert_eq!(resolve(root, "/blog/../../index.html"), Target::Missing);
+    }
+
+    #[test]
+    fn livereload_goes_before_the_end_of_the_body() {
+        let script = include_str!("./livereload.html");
+        let page = inject_livereload("<html><body><p>Hi</p></body></html>");
+        assert_eq!(page, format!("<html><body><p>Hi</p>{script}</body></html>"));
+
+        let page = inject_livereload("<p>No body</p>");
+        assert_eq!(page, format!("<p>No body</p>{script}"));
+    }
+
+    #[test]
+    fn text_types_are_utf8() {
+        let content_type = |name: &str| content_type(Path::new(name)).value.to_string();
+        assert_eq!(content_type("index.html"), "text/html; charset=utf-8");
+        assert_eq!(content_type("app.js"), "text/javascript; charset=utf-8");
+        assert_eq!(content_type("data.json"), "application/json; charset=utf-8");
+        assert_eq!(content_type("photo.png"), "image/png");
+        assert_eq!(content_type("blob"), "application/octet-stream");
+    }
+}
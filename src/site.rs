@@ -16,54 +16,179 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use color_eyre::eyre::{eyre, Result};
+use color_eyre::eyre::{bail, eyre, Context as _, Result};
 use handlebars::{
-    BlockContext, BlockParamHolder, Context, Handlebars, Helper, Output, RenderContext, RenderErrorReason, Renderable,
+    BlockContext, BlockParamHolder, Context, Handlebars, Helper, Output, RenderContext, RenderError,
+    RenderErrorReason, Renderable,
 };
+use hashbrown::{HashMap, HashSet};
 use ignore::WalkBuilder;
 use rayon::iter::{ParallelBridge, ParallelIterator};
 use std::{
-    io::Write,
+    collections::BTreeSet,
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
 };
 use tracing::{debug, info};
 use walkdir::WalkDir;
 
-use crate::config::Config;
+use crate::{
+    bundled::{self, Bundling},
+    config::Config,
+    deps::{ParamDeps, TemplateDeps},
+    favicons,
+    fingerprint::{self, Fingerprint},
+    manifest::{manifest_key, BuildManifest, FileRecord, MANIFEST_FILE},
+    frontmatter::{self, FrontMatter},
+    helpers, images, markdown,
+    theme::{self, Theme},
+};
+
+/// Name of the internal template pages with a layout are rendered
+/// through. It hands `page.content` to the layout exactly like
+/// `{{#theme}}` does, so layouts see it as `content`.
+const LAYOUT_TEMPLATE: &str = "_mllt/layout";
+const LAYOUT_TEMPLATE_SOURCE: &str = "{{#theme page.layout}}{{{page.content}}}{{/theme}}";
+
+/// What theme partials and layouts are named under, e.g. `theme/header`,
+/// whatever the folders holding them are called.
+const THEME_PREFIX: &str = "theme";
 
 pub struct Site<'a> {
     config: &'a Config,
     context: serde_json::Value,
     templates: Handlebars<'a>,
+    /// Maps registered template names back to the files they came from.
+    sources: HashMap<String, TemplateSource>,
+    /// Hash of every part of the context besides `params`, which
+    /// is tracked per-key instead.
+    context_hash: String,
+    /// Hash of the theme's helper scripts, which any page may call.
+    scripts_hash: String,
+    /// Hash of the assets, when fingerprinting or image processing
+    /// put their contents into the markup pages link to them with.
+    assets_hash: String,
     assets: Option<PathBuf>,
     out_dir: PathBuf,
+    /// What was built so far. Only persisted to the output folder
+    /// if the build manifest is enabled.
+    manifest: Mutex<BuildManifest>,
+}
+
+/// A registered template and what it depends on.
+struct TemplateSource {
+    path: PathBuf,
+    /// Hash of the template's source text.
+    hash: String,
+    deps: TemplateDeps,
+    front_matter: FrontMatter,
+    /// Lines of front matter stripped from the top of the file.
+    line_offset: usize,
 }
 
 impl<'a> Site<'a> {
     pub fn new(config: &'a Config) -> Result<Self> {
-        let handlebars = {
-            let mut handlebars = Handlebars::new(); 
-            handlebars.register_helper("theme", Box::new(ThemeHelper));
-            handlebars
+        let context: serde_json::Value = config.try_into()?;
+        let context_hash = {
+            let mut globals = context.clone();
+            if let Some(globals) = globals.as_object_mut() {
+                globals.remove("params");
+            }
+            // The `image` helper's markup depends on the image options.
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(globals.to_string().as_bytes());
+            hasher.update(serde_json::to_string(&config.images)?.as_bytes());
+            hasher.finalize().to_hex().to_string()
+        };
+
+        // Like the data folder, an empty assets folder
+        // doesn't survive being committed to git.
+        let assets = config.site.assets.clone().filter(|p| p.is_dir());
+
+        let assets_hash = match &assets {
+            Some(assets) if config.site.fingerprint != Fingerprint::Off || config.images.is_some() => {
+                fingerprint::hash_all(assets)?
+            }
+            _ => String::new(),
+        };
+
+        let manifest = if config.site.manifest {
+            BuildManifest::load(&config.site.out_dir)
+        } else {
+            BuildManifest::default()
         };
 
         Ok(Self {
             config,
-            context: config.try_into()?,
-            templates: handlebars,
-            assets: config.site.assets.clone(),
+            context,
+            templates: Self::registry(config),
+            sources: HashMap::new(),
+            context_hash,
+            scripts_hash: String::new(),
+            assets_hash,
+            assets,
             out_dir: config.site.out_dir.clone(),
+            manifest: Mutex::new(manifest),
         })
     }
 
     pub fn reload_templates(&mut self) -> Result<()> {
-        self.templates.clear_templates();
-        self.populate_templates()?;
+        // Start from a fresh registry so that deleted
+        // helper scripts don't linger.
+        self.templates = Self::registry(self.config);
+        self.sources.clear();
+        self.templates
+            .register_template_string(LAYOUT_TEMPLATE, LAYOUT_TEMPLATE_SOURCE)?;
+
+        let themes = self.config.themes()?;
+        theme::check_params(&themes, &self.config.params)?;
+
+        // Parents first, so that their children's scripts replace theirs.
+        let mut scripts_hash = blake3::Hasher::new();
+        for theme in themes.iter().rev() {
+            let hash = helpers::register_scripts(&mut self.templates, theme.dir.join("helpers"))?;
+            scripts_hash.update(hash.as_bytes());
+        }
+        self.scripts_hash = scripts_hash.finalize().to_hex().to_string();
+        self.populate_templates(&themes)?;
 
         Ok(())
     }
 
-    pub fn render(&self) -> Result<()> {
+    /// A template registry with every built-in helper, but no templates.
+    fn registry(config: &Config) -> Handlebars<'a> {
+        let mut handlebars = Handlebars::new();
+        handlebars.set_strict_mode(config.site.strict);
+        handlebars.set_engine(helpers::script_engine());
+        handlebars.register_helper("theme", Box::new(ThemeHelper));
+        helpers::register(&mut handlebars, config);
+        handlebars
+    }
+
+    /// Render every page and copy every asset, returning the paths
+    /// (relative to the output folder) of all the files this produced.
+    pub fn render(&self) -> Result<HashSet<PathBuf>> {
+        let mut produced = self.render_pages()?;
+        produced.extend(self.copy_assets()?);
+        if self.config.site.manifest {
+            produced.insert(MANIFEST_FILE.into());
+        }
+
+        Ok(produced)
+    }
+
+    /// Whether any template calls a helper that reads the assets,
+    /// like `asset_integrity`, whose output an asset edit changes.
+    pub fn uses_assets(&self) -> bool {
+        self.sources.values().any(|source| source.deps.assets)
+    }
+
+    /// Render every content page whose inputs changed since it was last
+    /// rendered, returning the paths of all pages, rendered or not.
+    pub fn render_pages(&self) -> Result<HashSet<PathBuf>> {
         // Create the output folder if it doesn't exist
         std::fs::create_dir_all(&self.out_dir)?;
 
@@ -77,52 +202,374 @@ impl<'a> Site<'a> {
             .parents(true)
             .build();
 
-        w.par_bridge().try_for_each(|entry| {
-            let entry = entry?;
-            if entry.path().extension().and_then(|s| s.to_str()) == Some("hbs") {
-                let template_name =
-                    Self::path_to_template_name(entry.path(), &self.config.site.content)?;
-
-                let final_output_path = self
-                    .config
-                    .site
-                    .out_dir
-                    .join(entry.path().strip_prefix(&self.config.site.content)?)
-                    .with_extension("html");
-
-                if let Some(parent) = final_output_path.parent() {
-                    std::fs::create_dir_all(parent)?;
-                }
-                let mut file = std::fs::File::create(final_output_path)?;
+        let rendered_count = AtomicUsize::new(0);
+        let mut rendered: Vec<(PathBuf, PathBuf)> = w
+            .par_bridge()
+            .map(|entry| {
+                let entry = entry?;
+                let page = match entry.path().extension().and_then(|s| s.to_str()) {
+                    Some("hbs" | "md") => self.render_page(entry.path(), &rendered_count)?,
+                    _ => None,
+                };
+                Ok(page.map(|page| (page, entry.path().to_owned())))
+            })
+            .filter_map(Result::transpose)
+            .collect::<Result<_>>()?;
 
-                let rendered = self
-                    .templates
-                    .render(template_name.as_str(), &self.context)?;
+        // Whichever page finished last would silently win.
+        rendered.sort();
+        if let Some(pair) = rendered.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            bail!(
+                "\"{}\" and \"{}\" are both rendered to \"{}\". Rename one, or set \
+                 `output` in its front matter.",
+                pair[0].1.display(),
+                pair[1].1.display(),
+                pair[0].0.display()
+            );
+        }
+        let pages: HashSet<PathBuf> = rendered.into_iter().map(|(page, _)| page).collect();
 
-                file.write_all(rendered.as_bytes())?;
+        let rendered_count = rendered_count.into_inner();
+        info!(
+            "Rendered {rendered_count} page{}, skipped {} unchanged.",
+            if rendered_count != 1 { "s" } else { "" },
+            pages.len() - rendered_count
+        );
+
+        // Forget about pages which no longer exist.
+        let keys: HashSet<String> = pages.iter().map(manifest_key).collect();
+        let mut manifest = self.manifest.lock().expect("build manifest poisoned");
+        manifest.pages.retain(|key, _| keys.contains(key));
+        if self.config.site.manifest {
+            manifest.save(&self.out_dir)?;
+        }
+
+        Ok(pages)
+    }
+
+    /// Render a single `.hbs` or `.md` content page, unless it's up to
+    /// date or a draft. Returns the page's path relative to the output
+    /// folder, or `None` if it was left out of the build.
+    fn render_page(&self, source: &Path, rendered_count: &AtomicUsize) -> Result<Option<PathBuf>> {
+        let content_dir = &self.config.site.content;
+        let is_markdown = source.extension().and_then(|s| s.to_str()) == Some("md");
+        let name = Self::path_to_template_name(source.with_extension(""), content_dir)?;
+
+        // Content templates had their front matter split off when they
+        // were registered. Markdown pages are read fresh every time.
+        let (front_matter, markdown_body) = if is_markdown {
+            let text = std::fs::read_to_string(source)?;
+            let split = frontmatter::split(&text)
+                .context(format!("Error reading \"{}\"", source.display()))?;
+            (split.front_matter, Some(split.body.to_owned()))
+        } else {
+            let front_matter = self
+                .sources
+                .get(&name)
+                .map(|s| s.front_matter.clone())
+                .unwrap_or_default();
+            (front_matter, None)
+        };
+
+        if front_matter.draft && !self.config.site.drafts {
+            debug!("Skipped (draft): {}", source.display());
+            return Ok(None);
+        }
+
+        let relative_path = match &front_matter.output {
+            Some(output) => output.clone(),
+            None => source.strip_prefix(content_dir)?.with_extension("html"),
+        };
+        let final_output_path = self.out_dir.join(&relative_path);
+
+        let mut page = self.page_context(source, &name, &relative_path)?;
+        page["params"] = front_matter.params.into();
+
+        // Markdown pages go through their layout instead of being
+        // templates themselves, so depend on the layout instead.
+        let (layout, root_templates) = match markdown_body {
+            Some(body) => {
+                let layout = front_matter
+                    .layout
+                    .as_deref()
+                    .or(self.config.site.default_layout.as_deref())
+                    .ok_or_else(|| {
+                        eyre!(
+                            "No layout for \"{}\". Set `layout` in its front matter \
+                             or `default_layout` in the config file.",
+                            source.display()
+                        )
+                    })?
+                    .to_owned();
+
+                page["content"] = markdown::to_html(&body).into();
+                (Some(layout.clone()), vec![layout])
+            }
+            None => match front_matter.layout {
+                Some(layout) => (Some(layout.clone()), vec![name.clone(), layout]),
+                None => (None, vec![name.clone()]),
+            },
+        };
+        if let Some(layout) = &layout {
+            page["layout"] = layout.clone().into();
+        }
+
+        let key = manifest_key(&relative_path);
+        let fingerprint = self.page_fingerprint(&root_templates, &page);
+        let up_to_date = fingerprint.is_some()
+            && final_output_path.is_file()
+            && self.manifest.lock().expect("build manifest poisoned").pages.get(&key)
+                == fingerprint.as_ref();
+        if up_to_date {
+            debug!("Skipped (unchanged): {}", final_output_path.display());
+            return Ok(Some(relative_path));
+        }
+
+        // Forgotten until it renders, so a page that fails to is
+        // always rebuilt, even if its inputs are later put back.
+        self.manifest
+            .lock()
+            .expect("build manifest poisoned")
+            .pages
+            .remove(&key);
+
+        let mut context = self.context.clone();
+        context["page"] = page;
+
+        let render = |name: &str, context: &serde_json::Value| {
+            self.templates
+                .render(name, context)
+                .map_err(|e| self.describe_render_error(e))
+        };
+
+        let rendered = match layout {
+            Some(_) if is_markdown => render(LAYOUT_TEMPLATE, &context)?,
+            Some(_) => {
+                context["page"]["content"] = render(&name, &context)?.into();
+                render(LAYOUT_TEMPLATE, &context)?
             }
-            
-            Ok::<(), color_eyre::Report>(())
-        })?;
+            None => render(&name, &context)?,
+        };
 
+        // Only written once rendering succeeded, so that an error
+        // doesn't leave the page empty.
+        if let Some(parent) = final_output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&final_output_path, rendered)?;
+        debug!("Rendered: {}", final_output_path.display());
+        rendered_count.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(fingerprint) = fingerprint {
+            let mut manifest = self.manifest.lock().expect("build manifest poisoned");
+            manifest.pages.insert(key, fingerprint);
+        }
+
+        Ok(Some(relative_path))
+    }
+
+    pub fn copy_assets(&self) -> Result<HashSet<PathBuf>> {
         // Copy the `assets` folder into the output folder
-        if let Some(assets) = self.assets.as_deref() {
+        let mut mirrored = if let Some(assets) = self.assets.as_deref() {
             info!("Copying static assets...");
-            Self::copy_if_newer(assets, &self.out_dir)?;
+            let fingerprint = self.config.site.fingerprint;
+            // Processed images are only published re-encoded, so any
+            // EXIF metadata in the originals never leaves the site.
+            let images = self.config.images.as_ref();
+            let skip = |p: &Path| images.is_some_and(|i| i.processes(p));
+            let (mut mirrored, stats) = if self.config.site.manifest {
+                let mut manifest = self.manifest.lock().expect("build manifest poisoned");
+                let result = Self::copy_if_changed(
+                    assets,
+                    &self.out_dir,
+                    fingerprint,
+                    skip,
+                    &mut manifest,
+                )?;
+                manifest.save(&self.out_dir)?;
+                result
+            } else {
+                Self::copy_if_newer(assets, &self.out_dir, fingerprint, skip)?
+            };
+            info!("{stats}");
+            if let Some(images) = &self.config.images {
+                mirrored.extend(images::process(images, assets, &self.out_dir)?);
+            }
+            mirrored
         } else {
             info!("No assets folder specified! Skipping...");
+            HashSet::new()
+        };
+
+        if self.config.site.bundled == Bundling::Link {
+            mirrored.extend(bundled::write(&self.out_dir, &self.linked_stylesheets())?);
         }
 
-        Ok(())
+        if let Some(favicon) = &self.config.site.favicon {
+            let name = self.config.params.get("title").and_then(|t| t.as_str());
+            let mut manifest = self.manifest.lock().expect("build manifest poisoned");
+            mirrored.extend(favicons::generate(
+                favicon,
+                &self.out_dir,
+                name,
+                &self.config.site.base_url(),
+                &mut manifest.favicon,
+            )?);
+            if self.config.site.manifest {
+                manifest.save(&self.out_dir)?;
+            }
+        }
+
+        Ok(mirrored)
+    }
+
+    /// The bundled stylesheets the templates link to. Found by looking
+    /// at the templates rather than at what was rendered, since pages
+    /// that were up to date don't call the `bundled` helper at all.
+    fn linked_stylesheets(&self) -> Vec<&'static bundled::Stylesheet> {
+        let deps = || self.sources.values().map(|source| &source.deps);
+        if deps().any(|deps| deps.any_bundled) {
+            return bundled::STYLESHEETS.iter().collect();
+        }
+        let names: BTreeSet<&str> = deps()
+            .flat_map(|deps| deps.bundled.iter().map(String::as_str))
+            .collect();
+        names.into_iter().filter_map(bundled::find).collect()
+    }
+
+    /// Build the `page` object made available to a single content
+    /// template, describing where it came from and where it's going.
+    fn page_context(
+        &self,
+        source: &Path,
+        template_name: &str,
+        relative_path: &Path,
+    ) -> Result<serde_json::Value> {
+        let to_url_path = |p: &Path| -> Result<String> {
+            Ok(p.to_str()
+                .ok_or(eyre!("Invalid template path"))?
+                .replace(std::path::MAIN_SEPARATOR, "/"))
+        };
+
+        let path = to_url_path(relative_path)?;
+        let name = to_url_path(&relative_path.with_extension(""))?;
+
+        // Link to directories rather than their index pages.
+        let url_path = match path.strip_suffix("index.html") {
+            Some(dir) if dir.is_empty() || dir.ends_with('/') => dir,
+            _ => path.as_str(),
+        };
+        let url = self.config.site.base_url().abs(url_path);
+
+        Ok(serde_json::json!({
+            "name": name,
+            "template": template_name,
+            "path": path,
+            "url": url,
+            "source": source.display().to_string(),
+        }))
+    }
+
+    /// Hash everything that goes into rendering a page: the templates
+    /// it (transitively) uses, the `params` keys they read, the rest of
+    /// the context and the page itself. Returns `None` if the page's
+    /// inputs can't be determined statically.
+    fn page_fingerprint(&self, templates: &[String], page: &serde_json::Value) -> Option<String> {
+        let mut used = BTreeSet::new();
+        let mut params = ParamDeps::default();
+        let mut queue = templates.to_vec();
+        while let Some(name) = queue.pop() {
+            if !used.insert(name.clone()) {
+                continue;
+            }
+
+            if let Some(source) = self.sources.get(&name) {
+                // Without hashes of the assets, there's no telling
+                // whether the ones the helpers look at changed.
+                let deps = &source.deps;
+                if deps.opaque || deps.clock || (deps.assets && self.assets_hash.is_empty()) {
+                    return None;
+                }
+                params.extend(&source.deps.params);
+                queue.extend(source.deps.partials.iter().cloned());
+            }
+        }
+
+        let mut hasher = blake3::Hasher::new();
+        let mut feed = |s: &str| {
+            hasher.update(s.as_bytes());
+            hasher.update(b"\0");
+        };
+
+        feed(env!("CARGO_PKG_VERSION"));
+        feed(&self.context_hash);
+        feed(&self.scripts_hash);
+        feed(&self.assets_hash);
+        feed(&page.to_string());
+
+        // Inline partials, or ones that don't exist (yet).
+        for name in &used {
+            feed(name);
+            feed(self.sources.get(name).map_or("-", |s| s.hash.as_str()));
+        }
+
+        match params {
+            ParamDeps::All => feed(&self.context["params"].to_string()),
+            ParamDeps::Keys(keys) => {
+                for key in keys {
+                    feed(&key);
+                    feed(&self.context["params"].get(&key).map(|v| v.to_string()).unwrap_or_default());
+                }
+            }
+        }
+
+        Some(hasher.finalize().to_hex().to_string())
     }
 
-    fn populate_templates(&mut self) -> Result<()> {
+    /// Point missing-variable errors (raised in strict mode) at the
+    /// file, line and column of the offending expression, since the
+    /// template name handlebars reports isn't always the file name.
+    fn describe_render_error(&self, e: RenderError) -> color_eyre::Report {
+        let RenderErrorReason::MissingVariable(variable) = e.reason() else {
+            return e.into();
+        };
+
+        let variable = variable.as_deref().unwrap_or("<unknown>");
+        let source = e.template_name.as_deref().and_then(|name| self.sources.get(name));
+        let file = match (source, e.template_name.as_deref()) {
+            (Some(source), _) => source.path.display().to_string(),
+            (None, Some(name)) => name.to_owned(),
+            (None, None) => "<unknown template>".to_owned(),
+        };
+
+        match (e.line_no, e.column_no) {
+            (Some(line), Some(col)) => {
+                let line = line + source.map_or(0, |s| s.line_offset);
+                eyre!("Missing variable `{variable}` in \"{file}\" at line {line}, column {col}")
+            }
+            _ => eyre!("Missing variable `{variable}` in \"{file}\""),
+        }
+    }
+
+    /// Register the partials of every theme in `themes`, the site's
+    /// layouts and the content templates. Templates of the same name
+    /// replace each other in that order, parent themes first.
+    fn populate_templates(&mut self, themes: &[Theme]) -> Result<()> {
         // Recursively scan the theme folder for .hbs partials
         // To support page transclusion, also add in .hbs templates
         // from the content directory, too.
+        // Files in `dir` are named as if they were in `names_as`, so
+        // that every theme in the chain and the layouts share names.
+        // Only content templates are pages with front matter; a
+        // partial may well start with a `---` of its own.
         // TODO: Parallelize
-        let scan_for_templates = |p: &Path, r: &mut Handlebars| -> Result<usize> {
-            let w = WalkBuilder::new(p)
+        let scan_for_templates = |dir: &Path,
+                                  names_as: &Path,
+                                  pages: bool,
+                                  r: &mut Handlebars,
+                                  sources: &mut HashMap<String, TemplateSource>|
+         -> Result<Vec<String>> {
+            let w = WalkBuilder::new(dir)
                 .git_global(false)
                 .git_exclude(false)
                 .git_ignore(false)
@@ -130,31 +577,107 @@ impl<'a> Site<'a> {
                 .parents(true)
                 .build();
 
-            let mut dbg_entry_count = 0usize;
+            let mut names = Vec::new();
             for entry in w {
                 let entry = entry?;
                 if entry.path().extension().and_then(|s| s.to_str()) == Some("hbs") {
-                    let template_name = Self::path_to_template_name(entry.path(), p)?;
+                    let template_name = Self::path_to_template_name(
+                        names_as.join(entry.path().strip_prefix(dir)?),
+                        names_as,
+                    )?;
 
-                    r.register_partial(&template_name, std::fs::read_to_string(entry.path())?)?;
+                    let source = std::fs::read_to_string(entry.path())?;
+                    let split = match pages {
+                        true => frontmatter::split(&source)
+                            .context(format!("Error reading \"{}\"", entry.path().display()))?,
+                        false => frontmatter::Split {
+                            front_matter: FrontMatter::default(),
+                            body: &source,
+                            lines: 0,
+                        },
+                    };
+                    r.register_partial(&template_name, split.body).map_err(|e| {
+                        let at = match e.pos() {
+                            Some((line, col)) => {
+                                format!(" at line {}, column {col}", line + split.lines)
+                            }
+                            None => String::new(),
+                        };
+                        color_eyre::Report::new(e)
+                            .wrap_err(format!("Invalid template \"{}\"{at}", entry.path().display()))
+                    })?;
+                    sources.insert(
+                        template_name.clone(),
+                        TemplateSource {
+                            path: entry.path().to_owned(),
+                            hash: blake3::hash(source.as_bytes()).to_hex().to_string(),
+                            deps: r
+                                .get_template(&template_name)
+                                .map(TemplateDeps::of)
+                                .unwrap_or_default(),
+                            front_matter: split.front_matter,
+                            line_offset: split.lines,
+                        },
+                    );
 
-                    dbg_entry_count += 1;
                     debug!("Registered template: {template_name}.");
+                    names.push(template_name);
                 }
             }
 
-            Ok(dbg_entry_count)
+            Ok(names)
         };
 
-        // Recursively scan the content folder for templates to render.
-        if let Some(tp) = &self.config.site.theme {
-            let cnt = scan_for_templates(tp, &mut self.templates)?;
+        let names_as = Path::new(THEME_PREFIX);
+        let mut theme_names = HashSet::new();
+        for theme in themes.iter().rev() {
+            theme_names.extend(scan_for_templates(
+                &theme.dir,
+                names_as,
+                false,
+                &mut self.templates,
+                &mut self.sources,
+            )?);
+        }
+        if !themes.is_empty() {
+            let cnt = theme_names.len();
+            info!(
+                "Registered {cnt} theme template{} from {}!",
+                if cnt != 1 { "s" } else { "" },
+                themes
+                    .iter()
+                    .map(|t| format!("\"{}\"", t.name()))
+                    .collect::<Vec<_>>()
+                    .join(" < ")
+            );
+        }
+
+        if let Some(layouts) = self.config.site.layouts.as_deref().filter(|p| p.is_dir()) {
+            let names = scan_for_templates(
+                layouts,
+                names_as,
+                false,
+                &mut self.templates,
+                &mut self.sources,
+            )?;
+            let overridden = names.iter().filter(|n| theme_names.contains(*n)).count();
             info!(
-                "Registered {cnt} theme template{}!",
-                if cnt != 1 { "s" } else { "" }
+                "Registered {} layout{}, overriding {overridden} theme template{}!",
+                names.len(),
+                if names.len() != 1 { "s" } else { "" },
+                if overridden != 1 { "s" } else { "" }
             );
         }
-        let cnt = scan_for_templates(&self.config.site.content, &mut self.templates)?;
+
+        // Recursively scan the content folder for templates to render.
+        let cnt = scan_for_templates(
+            &self.config.site.content,
+            &self.config.site.content,
+            true,
+            &mut self.templates,
+            &mut self.sources,
+        )?
+        .len();
         info!(
             "Registered {cnt} content template{}!",
             if cnt != 1 { "s" } else { "" }
@@ -163,43 +686,169 @@ impl<'a> Site<'a> {
         Ok(())
     }
 
-    fn copy_if_newer(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
+    /// Copies `src` into `dst`, except for files `skip` picks out,
+    /// returning the paths of all files now mirrored in `dst`,
+    /// whether they needed copying or not.
+    fn copy_if_newer(
+        src: impl AsRef<Path>,
+        dst: impl AsRef<Path>,
+        fingerprint: Fingerprint,
+        skip: impl Fn(&Path) -> bool,
+    ) -> Result<(HashSet<PathBuf>, CopyStats)> {
         let src = src.as_ref();
         let dst = dst.as_ref();
+        let mut mirrored = HashSet::new();
+        let mut stats = CopyStats::default();
 
         for entry in WalkDir::new(src) {
             let entry = entry?;
             let src_path = entry.path();
             let relative_path = src_path.strip_prefix(src)?;
-            let dst_path = dst.join(relative_path);
-
-            if src_path.is_file() {
-                let should_copy = if dst_path.exists() {
-                    let src_metadata = std::fs::metadata(src_path)?;
-                    let dst_metadata = std::fs::metadata(&dst_path)?;
-                    let src_modified = src_metadata.modified()?;
-                    let dst_modified = dst_metadata.modified()?;
-                    src_modified > dst_modified
-                } else {
-                    true
+
+            if src_path.is_file() && skip(relative_path) {
+                continue;
+            } else if src_path.is_file() {
+                let outputs = match fingerprint {
+                    Fingerprint::Off => vec![relative_path.to_owned()],
+                    mode => {
+                        let hash = FileRecord::of(src_path)?.hash;
+                        fingerprint::outputs(relative_path, &hash, mode)
+                    }
                 };
 
-                if should_copy {
-                    if let Some(parent) = dst_path.parent() {
-                        std::fs::create_dir_all(parent)?;
+                for output in outputs {
+                    let dst_path = dst.join(&output);
+                    let should_copy = if dst_path.exists() {
+                        let src_metadata = std::fs::metadata(src_path)?;
+                        let dst_metadata = std::fs::metadata(&dst_path)?;
+                        let src_modified = src_metadata.modified()?;
+                        let dst_modified = dst_metadata.modified()?;
+                        src_modified > dst_modified
+                    } else {
+                        true
+                    };
+
+                    if should_copy {
+                        if let Some(parent) = dst_path.parent() {
+                            std::fs::create_dir_all(parent)?;
+                        }
+                        std::fs::copy(src_path, &dst_path)?;
+                        stats.copied += 1;
+                        debug!("Copied: {}", dst_path.display());
+                    } else {
+                        stats.skipped += 1;
+                        debug!("Skipped (source not newer): {}", dst_path.display());
                     }
-                    std::fs::copy(src_path, &dst_path)?;
-                    debug!("Copied: {}", dst_path.display());
-                } else {
-                    debug!("Skipped (source not newer): {}", dst_path.display());
+                    mirrored.insert(output);
+                }
+            } else {
+                let dst_path = dst.join(relative_path);
+                if src_path.is_dir() && !dst_path.exists() {
+                    std::fs::create_dir_all(&dst_path)?;
+                    debug!("Created directory: {}", dst_path.display());
                 }
-            } else if src_path.is_dir() && !dst_path.exists() {
-                std::fs::create_dir_all(&dst_path)?;
-                debug!("Created directory: {}", dst_path.display());
             }
         }
 
-        Ok(())
+        Ok((mirrored, stats))
+    }
+
+    /// Like [`Self::copy_if_newer`], but decides what to copy by
+    /// comparing content hashes against the build manifest. Assets
+    /// recorded in the manifest whose source has since disappeared, or
+    /// is now skipped, are deleted from `dst`, as are outdated
+    /// fingerprinted copies.
+    fn copy_if_changed(
+        src: impl AsRef<Path>,
+        dst: impl AsRef<Path>,
+        fingerprint: Fingerprint,
+        skip: impl Fn(&Path) -> bool,
+        manifest: &mut BuildManifest,
+    ) -> Result<(HashSet<PathBuf>, CopyStats)> {
+        let src = src.as_ref();
+        let dst = dst.as_ref();
+        let mut mirrored = HashSet::new();
+        let mut stats = CopyStats::default();
+        let mut seen = HashSet::new();
+
+        for entry in WalkDir::new(src) {
+            let entry = entry?;
+            let src_path = entry.path();
+            let relative_path = src_path.strip_prefix(src)?;
+
+            if src_path.is_file() && skip(relative_path) {
+                continue;
+            } else if src_path.is_file() {
+                let key = manifest_key(relative_path);
+                let record = FileRecord::of(src_path)?;
+                let previous = manifest.assets.insert(key.clone(), record.clone());
+
+                // A fingerprinted copy of the old contents would otherwise
+                // linger forever, since nothing refers to it by name.
+                if let Some(previous) = previous.as_ref().filter(|p| p.hash != record.hash) {
+                    let stale = dst.join(fingerprint::fingerprinted(relative_path, &previous.hash));
+                    if stale.is_file() {
+                        std::fs::remove_file(&stale)?;
+                        stats.removed += 1;
+                        debug!("Removed: {}", stale.display());
+                    }
+                }
+
+                for output in fingerprint::outputs(relative_path, &record.hash, fingerprint) {
+                    let dst_path = dst.join(&output);
+
+                    // The size check catches output files that were
+                    // modified or truncated behind our back.
+                    let up_to_date = previous.as_ref() == Some(&record)
+                        && std::fs::metadata(&dst_path).is_ok_and(|m| m.len() == record.size);
+
+                    if up_to_date {
+                        stats.skipped += 1;
+                        debug!("Skipped (unchanged): {}", dst_path.display());
+                    } else {
+                        if let Some(parent) = dst_path.parent() {
+                            std::fs::create_dir_all(parent)?;
+                        }
+                        std::fs::copy(src_path, &dst_path)?;
+                        stats.copied += 1;
+                        debug!("Copied: {}", dst_path.display());
+                    }
+                    mirrored.insert(output);
+                }
+
+                seen.insert(key);
+            } else {
+                let dst_path = dst.join(relative_path);
+                if src_path.is_dir() && !dst_path.exists() {
+                    std::fs::create_dir_all(&dst_path)?;
+                    debug!("Created directory: {}", dst_path.display());
+                }
+            }
+        }
+
+        let removed: Vec<String> = manifest
+            .assets
+            .keys()
+            .filter(|key| !seen.contains(*key))
+            .cloned()
+            .collect();
+        for key in removed {
+            let Some(record) = manifest.assets.remove(&key) else {
+                continue;
+            };
+
+            // Whichever copies the old fingerprinting mode left behind.
+            let fingerprinted = fingerprint::fingerprinted(Path::new(&key), &record.hash);
+            for dst_path in [dst.join(&key), dst.join(fingerprinted)] {
+                if dst_path.is_file() {
+                    std::fs::remove_file(&dst_path)?;
+                    stats.removed += 1;
+                    debug!("Removed: {}", dst_path.display());
+                }
+            }
+        }
+
+        Ok((mirrored, stats))
     }
 
     fn path_to_template_name(
@@ -223,6 +872,27 @@ impl<'a> Site<'a> {
     }
 }
 
+/// Counts of what happened to each asset during a copy.
+#[derive(Default, Debug)]
+struct CopyStats {
+    copied: usize,
+    skipped: usize,
+    removed: usize,
+}
+
+impl std::fmt::Display for CopyStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Copied {} asset{}, skipped {}, removed {}.",
+            self.copied,
+            if self.copied != 1 { "s" } else { "" },
+            self.skipped,
+            self.removed
+        )
+    }
+}
+
 #[derive(Clone, Copy)]
 struct ThemeHelper;
 
@@ -278,3 +948,418 @@ impl handlebars::HelperDef for ThemeHelper {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A site in a temporary folder, made of `files`.
+    fn project(files: &[(&str, &str)]) -> (tempfile::TempDir, Config) {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, contents) in files {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
+        let mut config = Config::default();
+        let site = &mut config.site;
+        site.content = dir.path().join("content");
+        site.theme = Some(dir.path().join("theme"));
+        site.layouts = Some(dir.path().join("layouts"));
+        site.assets = Some(dir.path().join("assets"));
+        site.data = Some(dir.path().join("data"));
+        site.out_dir = dir.path().join("output");
+        (dir, config)
+    }
+
+    fn write(dir: &tempfile::TempDir, name: &str, contents: &str) {
+        std::fs::write(dir.path().join(name), contents).unwrap();
+    }
+
+    /// The fingerprint of the `index` page.
+    fn fingerprint(config: &Config) -> Option<String> {
+        let mut site = Site::new(config).unwrap();
+        site.reload_templates().unwrap();
+        site.page_fingerprint(&["content/index".to_owned()], &serde_json::json!({}))
+    }
+
+    /// Render every page, returning the output folder's `index.html`.
+    fn build(config: &Config) -> Result<String> {
+        let mut site = Site::new(config)?;
+        site.reload_templates()?;
+        site.render_pages()?;
+        Ok(std::fs::read_to_string(config.site.out_dir.join("index.html"))?)
+    }
+
+    #[test]
+    fn partials_have_no_front_matter() {
+        let (_dir, config) = project(&[
+            ("content/index.hbs", "{{> theme/divider}}"),
+            ("theme/divider.hbs", "---\nnot front matter\n---\n<hr>"),
+        ]);
+        let html = build(&config).unwrap();
+        assert_eq!(html, "---\nnot front matter\n---\n<hr>");
+    }
+
+    #[test]
+    fn toml_dates_reach_the_date_helper() {
+        let (_dir, config) = project(&[
+            (
+                "content/index.hbs",
+                "+++\npublished = 2024-05-02T10:30:00\n+++\n{{date page.params.published \"%B %-d, %Y\"}}",
+            ),
+            ("theme/footer.hbs", ""),
+        ]);
+        assert_eq!(build(&config).unwrap(), "May 2, 2024");
+    }
+
+    #[test]
+    fn fingerprinted_asset_links() {
+        let (_dir, mut config) = project(&[
+            (
+                "content/index.hbs",
+                "{{asset \"css/a.css\"}} {{asset_integrity \"css/a.css\"}}",
+            ),
+            ("assets/css/a.css", "abc"),
+            ("theme/footer.hbs", ""),
+        ]);
+        config.site.fingerprint = Fingerprint::Replace;
+        let hash = FileRecord::of(config.site.assets.as_ref().unwrap().join("css/a.css"))
+            .unwrap()
+            .hash;
+        assert_eq!(
+            build(&config).unwrap(),
+            format!(
+                "/css/a.{}.css sha384-ywB1P0WjXou1oD1pmsZQBycsMqsO3tFjGotgWkP/W+2AhgcroefMI1i67KE0yCWn",
+                &hash[..10]
+            )
+        );
+    }
+
+    #[test]
+    fn asset_edits_reach_integrity_hashes() {
+        let (dir, config) = project(&[
+            ("content/index.hbs", "{{asset_integrity \"a.css\"}}"),
+            ("assets/a.css", "abc"),
+            ("theme/footer.hbs", ""),
+        ]);
+        let mut site = Site::new(&config).unwrap();
+        site.reload_templates().unwrap();
+        assert!(site.uses_assets());
+        site.render_pages().unwrap();
+        let index = config.site.out_dir.join("index.html");
+        let before = std::fs::read_to_string(&index).unwrap();
+
+        // What `mllt serve` does after an asset edit.
+        write(&dir, "assets/a.css", "abcd");
+        site.reload_templates().unwrap();
+        site.render_pages().unwrap();
+        assert_ne!(std::fs::read_to_string(&index).unwrap(), before);
+
+        write(&dir, "content/index.hbs", "{{asset_url \"a.css\"}}");
+        site.reload_templates().unwrap();
+        assert!(site.uses_assets());
+        write(&dir, "content/index.hbs", "plain");
+        site.reload_templates().unwrap();
+        assert!(!site.uses_assets());
+    }
+
+    #[test]
+    fn manifest_keys_assets_by_source_path() {
+        let (dir, mut config) = project(&[
+            ("content/index.hbs", ""),
+            ("assets/css/a.css", "abc"),
+            ("theme/footer.hbs", ""),
+        ]);
+        config.site.fingerprint = Fingerprint::Replace;
+        config.site.manifest = true;
+        let site = Site::new(&config).unwrap();
+        let produced = site.copy_assets().unwrap();
+
+        let manifest = BuildManifest::load(&config.site.out_dir);
+        let record = &manifest.assets["css/a.css"];
+        let output = fingerprint::fingerprinted(Path::new("css/a.css"), &record.hash);
+        assert_eq!(produced, HashSet::from([output.clone()]));
+        assert!(config.site.out_dir.join(output).is_file());
+
+        // A new version replaces the old copy under the same key.
+        write(&dir, "assets/css/a.css", "abcd");
+        Site::new(&config).unwrap().copy_assets().unwrap();
+        let manifest = BuildManifest::load(&config.site.out_dir);
+        assert_eq!(manifest.assets.len(), 1);
+        assert_ne!(manifest.assets["css/a.css"].hash, record.hash);
+        assert!(!produced.iter().any(|p| config.site.out_dir.join(p).exists()));
+    }
+
+    #[test]
+    fn only_linked_stylesheets_are_written() {
+        let (_dir, mut config) = project(&[
+            ("content/index.hbs", "{{{bundled \"reset\"}}}"),
+            ("theme/footer.hbs", ""),
+        ]);
+        config.site.bundled = Bundling::Link;
+        let mut site = Site::new(&config).unwrap();
+        site.reload_templates().unwrap();
+        let produced = site.render().unwrap();
+        assert!(produced.contains(Path::new("_bundled/reset-1.0.0.css")), "{produced:?}");
+        assert!(!config.site.out_dir.join("_bundled/normalize-8.0.1.css").exists());
+    }
+
+    #[test]
+    fn pruning_keeps_dot_relative_outputs() {
+        let (_dir, config) = project(&[
+            ("content/index.hbs", "index"),
+            ("content/about.hbs", "+++\noutput = \"./about.html\"\n+++\nabout"),
+            ("theme/footer.hbs", ""),
+        ]);
+        let mut site = Site::new(&config).unwrap();
+        site.reload_templates().unwrap();
+        let produced = site.render_pages().unwrap();
+        assert!(produced.contains(Path::new("about.html")), "{produced:?}");
+
+        let stale = crate::prune::prune(&config.site.out_dir, &produced, &[], false).unwrap();
+        assert!(stale.is_empty(), "{stale:?}");
+        assert!(config.site.out_dir.join("about.html").is_file());
+    }
+
+    #[test]
+    fn failed_pages_are_rebuilt() {
+        let (dir, mut config) = project(&[
+            ("content/index.hbs", "first"),
+            ("theme/footer.hbs", ""),
+        ]);
+        config.site.manifest = true;
+        config.site.strict = true;
+        assert_eq!(build(&config).unwrap(), "first");
+
+        // The page from the last good build is left alone.
+        write(&dir, "content/index.hbs", "{{missing}}");
+        assert!(build(&config).is_err());
+        let index = config.site.out_dir.join("index.html");
+        assert_eq!(std::fs::read_to_string(&index).unwrap(), "first");
+
+        write(&dir, "content/index.hbs", "first");
+        assert_eq!(build(&config).unwrap(), "first");
+    }
+
+    #[test]
+    fn processed_images_are_published_without_exif() {
+        use image::{codecs::jpeg::JpegEncoder, DynamicImage, ImageEncoder};
+
+        let (dir, mut config) = project(&[
+            ("content/index.hbs", ""),
+            ("assets/a.css", "abc"),
+            ("theme/footer.hbs", ""),
+        ]);
+        let exif = b"MM\0\x2a\0\0\0\x08\0\0GPS 51.5007N 0.1246W".to_vec();
+        let mut jpeg = Vec::new();
+        let mut encoder = JpegEncoder::new(&mut jpeg);
+        encoder.set_exif_metadata(exif.clone()).unwrap();
+        let image = DynamicImage::new_rgb8(8, 4);
+        encoder
+            .write_image(image.as_bytes(), 8, 4, image.color().into())
+            .unwrap();
+        assert!(jpeg.windows(exif.len()).any(|w| w == exif));
+        std::fs::write(dir.path().join("assets/photo.jpg"), &jpeg).unwrap();
+
+        let mut images: images::ImageOptions = toml::from_str("widths = [4]").unwrap();
+        images.cache = dir.path().join("cache");
+        config.images = Some(images);
+
+        for (manifest, fingerprint) in [(false, Fingerprint::Off), (true, Fingerprint::Alongside)] {
+            config.site.manifest = manifest;
+            config.site.fingerprint = fingerprint;
+            let produced = Site::new(&config).unwrap().copy_assets().unwrap();
+            assert!(produced.contains(Path::new("a.css")), "{produced:?}");
+            assert!(produced.contains(Path::new("photo.4w.jpg")), "{produced:?}");
+            assert!(!produced.iter().any(|p| p.starts_with("photo.jpg")));
+
+            for entry in WalkDir::new(&config.site.out_dir) {
+                let entry = entry.unwrap();
+                if entry.file_type().is_file() {
+                    let bytes = std::fs::read(entry.path()).unwrap();
+                    assert!(
+                        !bytes.windows(4).any(|w| w == b"Exif"),
+                        "{} has EXIF data",
+                        entry.path().display()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pages_rendered_to_the_same_file() {
+        let (_dir, config) = project(&[
+            ("content/index.hbs", "index"),
+            ("content/home.hbs", "+++\noutput = \"index.html\"\n+++\nhome"),
+            ("theme/footer.hbs", ""),
+        ]);
+        let error = build(&config).unwrap_err().to_string();
+        assert!(error.contains("home.hbs\" and \""), "{error}");
+        assert!(error.contains("are both rendered to \"index.html\""), "{error}");
+    }
+
+    #[test]
+    fn markdown_and_template_of_the_same_name() {
+        let (_dir, mut config) = project(&[
+            ("content/index.hbs", "index"),
+            ("content/index.md", "# Index"),
+            ("theme/main.hbs", "{{{content}}}"),
+        ]);
+        config.site.default_layout = Some("theme/main".to_owned());
+        let error = build(&config).unwrap_err().to_string();
+        assert!(error.contains("index.hbs\" and \""), "{error}");
+        assert!(error.contains("index.md\" are both rendered"), "{error}");
+    }
+
+    #[test]
+    fn markdown_layout_from_front_matter() {
+        let (_dir, mut config) = project(&[
+            (
+                "content/index.md",
+                "+++\nlayout = \"theme/other\"\n+++\n*Hi*",
+            ),
+            ("theme/main.hbs", "main"),
+            ("theme/other.hbs", "<main>{{{content}}}</main>"),
+        ]);
+        config.site.default_layout = Some("theme/main".to_owned());
+        assert_eq!(build(&config).unwrap(), "<main><p><em>Hi</em></p>\n</main>");
+    }
+
+    #[test]
+    fn theme_partials_are_named_theme() {
+        let (dir, mut config) = project(&[
+            ("content/index.hbs", "{{> theme/header}}{{> theme/footer}}"),
+            ("themes/base/theme.toml", "name = \"base\"\nversion = \"1.0.0\""),
+            ("themes/base/header.hbs", "base header "),
+            ("themes/base/footer.hbs", "base footer"),
+            (
+                "themes/fancy/theme.toml",
+                "name = \"fancy\"\nversion = \"1.0.0\"\nparent = \"../base\"",
+            ),
+            ("themes/fancy/header.hbs", "fancy header "),
+            ("overrides/footer.hbs", "own footer"),
+        ]);
+        config.site.theme = Some(dir.path().join("themes/fancy"));
+        config.site.layouts = Some(dir.path().join("overrides"));
+        assert_eq!(build(&config).unwrap(), "fancy header own footer");
+    }
+
+    #[test]
+    fn missing_variable_in_a_partial() {
+        let (dir, mut config) = project(&[
+            ("content/index.hbs", "+++\ntitle = \"Home\"\n+++\n{{> theme/header}}"),
+            ("theme/header.hbs", "<header>\n  <h1>{{params.nope}}</h1>\n</header>"),
+        ]);
+        config.site.strict = true;
+        let error = build(&config).unwrap_err().to_string();
+        let header = dir.path().join("theme/header.hbs");
+        assert_eq!(
+            error,
+            format!(
+                "Missing variable `params.nope` in \"{}\" at line 2, column 7",
+                header.display()
+            )
+        );
+
+        // Lines are counted from the top of the file, front matter and all.
+        write(&dir, "content/index.hbs", "+++\ntitle = \"Home\"\n+++\n\n{{page.nope}}");
+        let error = build(&config).unwrap_err().to_string();
+        assert!(error.ends_with("index.hbs\" at line 5, column 1"), "{error}");
+    }
+
+    #[test]
+    fn broken_partial() {
+        let (dir, config) = project(&[
+            ("content/index.hbs", "+++\ntitle = \"Home\"\n+++\n{{#if page}}"),
+            ("theme/header.hbs", "<header>\n{{#if params.title}}\n</header>"),
+        ]);
+        let error = build(&config).unwrap_err().to_string();
+        let header = dir.path().join("theme/header.hbs");
+        assert_eq!(
+            error,
+            format!("Invalid template \"{}\" at line 3, column 10", header.display())
+        );
+
+        write(&dir, "theme/header.hbs", "<header></header>");
+        let error = build(&config).unwrap_err().to_string();
+        assert!(error.ends_with("index.hbs\" at line 4, column 13"), "{error}");
+    }
+
+    const SITE: &[(&str, &str)] = &[
+        ("content/index.hbs", "{{> theme/header}}"),
+        ("theme/header.hbs", "<h1>{{params.title}}</h1>"),
+        ("theme/footer.hbs", "<small>{{params.desc}}</small>"),
+    ];
+
+    #[test]
+    fn used_partial_change_invalidates() {
+        let (dir, config) = project(SITE);
+        let before = fingerprint(&config).unwrap();
+
+        write(&dir, "theme/footer.hbs", "<small>changed</small>");
+        assert_eq!(fingerprint(&config).unwrap(), before);
+
+        write(&dir, "theme/header.hbs", "<h2>{{params.title}}</h2>");
+        assert_ne!(fingerprint(&config).unwrap(), before);
+    }
+
+    #[test]
+    fn used_param_change_invalidates() {
+        let (_dir, mut config) = project(SITE);
+        let before = fingerprint(&config).unwrap();
+
+        config.params.insert("desc".into(), "Only in the footer".into());
+        assert_eq!(fingerprint(&config).unwrap(), before);
+
+        config.params.insert("title".into(), "A new title".into());
+        assert_ne!(fingerprint(&config).unwrap(), before);
+    }
+
+    #[test]
+    fn whole_context_reads_depend_on_every_param() {
+        let (_dir, mut config) = project(&[
+            ("content/index.hbs", "{{json @root}}"),
+            ("theme/footer.hbs", ""),
+        ]);
+        let before = fingerprint(&config).unwrap();
+        config.params.insert("desc".into(), "Anything at all".into());
+        assert_ne!(fingerprint(&config).unwrap(), before);
+    }
+
+    #[test]
+    fn layout_override_invalidates() {
+        let (dir, config) = project(SITE);
+        let before = fingerprint(&config).unwrap();
+
+        std::fs::create_dir(dir.path().join("layouts")).unwrap();
+        write(&dir, "layouts/header.hbs", "<h1>Overridden</h1>");
+        assert_ne!(fingerprint(&config).unwrap(), before);
+    }
+
+    #[test]
+    fn clock_is_always_dirty() {
+        let (_dir, config) = project(&[
+            ("content/index.hbs", "{{> theme/footer}}"),
+            ("theme/footer.hbs", "&copy; {{date \"now\" \"%Y\"}}"),
+        ]);
+        assert_eq!(fingerprint(&config), None);
+    }
+
+    #[test]
+    fn unhashed_assets_are_always_dirty() {
+        let files = [
+            ("content/index.hbs", "<link href=\"{{asset_url \"style.css\"}}\">"),
+            ("assets/style.css", "body {}"),
+            ("theme/footer.hbs", ""),
+        ];
+        let (_dir, mut config) = project(&files);
+        assert_eq!(fingerprint(&config), None);
+
+        // Fingerprinting hashes every asset, so changes are noticed.
+        config.site.fingerprint = Fingerprint::Alongside;
+        assert!(fingerprint(&config).is_some());
+    }
+}
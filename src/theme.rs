@@ -0,0 +1,242 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Theme packages. A theme is a folder of partials, optionally
+//! described by a `theme.toml` that can name a parent theme to
+//! inherit partials, helper scripts and icons from.
+
+use color_eyre::eyre::{bail, Context as _, Result};
+use hashbrown::HashMap;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// File describing a theme, in the theme's folder.
+pub const MANIFEST: &str = "theme.toml";
+
+/// How many parents a theme may have, which mostly
+/// exists to catch themes inheriting from themselves.
+const MAX_DEPTH: usize = 16;
+
+/// A theme's `theme.toml`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+
+    /// `params` keys the theme's templates can't do without,
+    /// checked before anything is rendered. Nested keys are
+    /// written with dots, like `made_with.link`.
+    #[serde(default)]
+    pub required_params: Vec<String>,
+
+    /// Folder of the theme this one inherits from,
+    /// relative to this theme's folder.
+    pub parent: Option<PathBuf>,
+}
+
+/// A theme folder and what its `theme.toml` says about it, if it has one.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub dir: PathBuf,
+    pub manifest: Option<Manifest>,
+}
+
+impl Theme {
+    pub fn load(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        if !dir.is_dir() {
+            bail!("Theme folder \"{}\" does not exist.", dir.display());
+        }
+
+        let path = dir.join(MANIFEST);
+        let manifest = match std::fs::read_to_string(&path) {
+            Ok(source) => Some(
+                toml::from_str(&source).context(format!("Error reading \"{}\"", path.display()))?,
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).context(format!("Error reading \"{}\"", path.display())),
+        };
+
+        Ok(Self { dir, manifest })
+    }
+
+    /// The theme's name, or its folder's if it has no `theme.toml`.
+    pub fn name(&self) -> String {
+        match &self.manifest {
+            Some(manifest) => manifest.name.clone(),
+            None => self
+                .dir
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+}
+
+/// The theme in `dir` followed by its parent, grandparent and so
+/// on. Partials are looked up through this chain in order.
+pub fn chain(dir: impl Into<PathBuf>) -> Result<Vec<Theme>> {
+    let mut chain = vec![Theme::load(dir)?];
+    while let Some(parent) = chain
+        .last()
+        .and_then(|t| Some(t.dir.join(t.manifest.as_ref()?.parent.as_ref()?)))
+    {
+        if chain.len() > MAX_DEPTH {
+            bail!(
+                "Theme \"{}\" has too many parent themes; does it inherit from itself?",
+                chain[0].name()
+            );
+        }
+
+        let child = chain.last().map(Theme::name).unwrap_or_default();
+        let parent = Theme::load(&parent)
+            .context(format!("Error loading the parent of theme \"{child}\""))?;
+        chain.push(parent);
+    }
+
+    Ok(chain)
+}
+
+/// Make sure `params` has every value the themes in `chain` require.
+pub fn check_params(chain: &[Theme], params: &HashMap<String, serde_json::Value>) -> Result<()> {
+    for theme in chain {
+        let Some(manifest) = &theme.manifest else {
+            continue;
+        };
+
+        for key in &manifest.required_params {
+            let mut parts = key.split('.');
+            let first = parts.next().and_then(|k| params.get(k));
+            let value = parts.fold(first, |value, k| value?.get(k));
+            if value.is_none_or(serde_json::Value::is_null) {
+                bail!(
+                    "Theme \"{}\" requires `params.{key}`, which isn't set in the config.",
+                    theme.name()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A theme folder under `root` named `name`, inheriting from `parent`.
+    fn theme(root: &tempfile::TempDir, name: &str, parent: Option<&str>) -> PathBuf {
+        let dir = root.path().join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut manifest = format!("name = \"{name}\"\nversion = \"1.0.0\"\n");
+        if let Some(parent) = parent {
+            manifest += &format!("parent = \"../{parent}\"\n");
+        }
+        std::fs::write(dir.join(MANIFEST), manifest).unwrap();
+        dir
+    }
+
+    fn names(chain: &[Theme]) -> Vec<String> {
+        chain.iter().map(Theme::name).collect()
+    }
+
+    #[test]
+    fn chain_runs_from_child_to_ancestors() {
+        let root = tempfile::tempdir().unwrap();
+        theme(&root, "base", None);
+        theme(&root, "team", Some("base"));
+        let site = theme(&root, "site", Some("team"));
+        assert_eq!(names(&chain(site).unwrap()), ["site", "team", "base"]);
+    }
+
+    #[test]
+    fn theme_without_manifest() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("plain");
+        std::fs::create_dir(&dir).unwrap();
+        assert_eq!(names(&chain(&dir).unwrap()), ["plain"]);
+    }
+
+    #[test]
+    fn missing_parent() {
+        let root = tempfile::tempdir().unwrap();
+        let site = theme(&root, "site", Some("gone"));
+        let e = chain(site).unwrap_err();
+        assert!(
+            format!("{e:#}").contains("parent of theme \"site\""),
+            "{e:#}"
+        );
+    }
+
+    #[test]
+    fn cycles_are_caught() {
+        let root = tempfile::tempdir().unwrap();
+        theme(&root, "a", Some("b"));
+        let b = theme(&root, "b", Some("a"));
+        let e = chain(b).unwrap_err();
+        assert!(e.to_string().contains("inherit from itself"), "{e}");
+
+        let selfish = theme(&root, "selfish", Some("selfish"));
+        assert!(chain(selfish).is_err());
+    }
+
+    #[test]
+    fn deep_chains_are_fine() {
+        let root = tempfile::tempdir().unwrap();
+        let mut parent = None;
+        let mut name = String::new();
+        for i in 0..=MAX_DEPTH {
+            name = format!("t{i}");
+            theme(&root, &name, parent.as_deref());
+            parent = Some(name.clone());
+        }
+        assert_eq!(chain(root.path().join(name)).unwrap().len(), MAX_DEPTH + 1);
+    }
+
+    #[test]
+    fn required_params() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = theme(&root, "site", None);
+        std::fs::write(
+            dir.join(MANIFEST),
+            "name = \"site\"\nversion = \"1.0.0\"\n\
+             required_params = [\"title\", \"made_with.link\"]\n",
+        )
+        .unwrap();
+        let chain = chain(dir).unwrap();
+
+        let params = |json: serde_json::Value| -> HashMap<String, serde_json::Value> {
+            serde_json::from_value(json).unwrap()
+        };
+        let ok = params(serde_json::json!({"title": "t", "made_with": {"link": "l"}}));
+        assert!(check_params(&chain, &ok).is_ok());
+
+        for missing in [
+            serde_json::json!({"made_with": {"link": "l"}}),
+            serde_json::json!({"title": "t"}),
+            serde_json::json!({"title": "t", "made_with": "l"}),
+            serde_json::json!({"title": "t", "made_with": {"link": null}}),
+        ] {
+            assert!(check_params(&chain, &params(missing)).is_err());
+        }
+        let e = check_params(&chain, &params(serde_json::json!({"title": "t"}))).unwrap_err();
+        assert!(e.to_string().contains("`params.made_with.link`"), "{e}");
+    }
+}
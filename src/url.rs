@@ -0,0 +1,221 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+/// A site's `baseURL`, split into the origin it's served from and
+/// the path of the site's root under that origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseUrl {
+    /// Scheme, host and port, e.g. `https://example.com`. Empty if
+    /// the base URL is just a path, like `/~user/`.
+    origin: String,
+
+    /// Always starts and ends with `/`.
+    path: String,
+}
+
+impl BaseUrl {
+    /// Accepts anything from `example.com` (assumed to be HTTPS)
+    /// to `https://example.com/~user/`, or a bare path.
+    pub fn parse(base: &str) -> Self {
+        let base = base.trim();
+        let (origin, path) = if let Some(scheme_end) = base.find("://") {
+            let host_start = scheme_end + 3;
+            let path_start = base[host_start..]
+                .find('/')
+                .map_or(base.len(), |i| host_start + i);
+            (base[..path_start].to_owned(), &base[path_start..])
+        } else if base.is_empty() || base.starts_with('/') {
+            (String::new(), base)
+        } else {
+            let path_start = base.find('/').unwrap_or(base.len());
+            (
+                format!("https://{}", &base[..path_start]),
+                &base[path_start..],
+            )
+        };
+
+        let path = match path.trim_matches('/') {
+            "" => "/".to_owned(),
+            path => format!("/{path}/"),
+        };
+
+        Self { origin, path }
+    }
+
+    /// `target` as a URL relative to the host, under the site's root.
+    /// URLs with a scheme (or protocol-relative ones) are left alone.
+    pub fn rel(&self, target: &str) -> String {
+        if is_absolute(target) {
+            return target.to_owned();
+        }
+
+        let mut target = target;
+        while let Some(rest) = target.strip_prefix("./") {
+            target = rest;
+        }
+        format!("{}{}", self.path, target.trim_start_matches('/'))
+    }
+
+    /// `target` as a full URL, including the scheme and host.
+    pub fn abs(&self, target: &str) -> String {
+        if is_absolute(target) {
+            return target.to_owned();
+        }
+
+        format!("{}{}", self.origin, self.rel(target))
+    }
+}
+
+/// Why `url` can't be used as a link, if it can't: it's empty,
+/// contains whitespace, or has a `://` but no host after it.
+pub fn check(url: &str) -> Result<(), String> {
+    if url.is_empty() {
+        return Err("must not be empty".into());
+    }
+    if url.contains(char::is_whitespace) {
+        return Err("must not contain whitespace".into());
+    }
+    if let Some((_, rest)) = url.split_once("://") {
+        if rest
+            .split(['/', '?', '#'])
+            .next()
+            .unwrap_or_default()
+            .is_empty()
+        {
+            return Err("must have a host after `://`".into());
+        }
+    }
+    Ok(())
+}
+
+/// Whether `url` starts with a scheme (`https:`, `mailto:`, ...)
+/// or is protocol-relative (`//cdn.example.com/...`).
+pub fn is_absolute(url: &str) -> bool {
+    if url.starts_with("//") {
+        return true;
+    }
+
+    match url.split_once(':') {
+        Some((scheme, _)) => {
+            let mut chars = scheme.chars();
+            chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scheme_less_base_is_https() {
+        // What `Config::default` writes.
+        let base = BaseUrl::parse("example.com");
+        assert_eq!(base.rel("style.css"), "/style.css");
+        assert_eq!(base.abs("style.css"), "https://example.com/style.css");
+        assert_eq!(base.abs(""), "https://example.com/");
+    }
+
+    #[test]
+    fn subpath_base() {
+        for raw in [
+            "https://example.com/~user",
+            "https://example.com/~user/",
+            "https://example.com//~user//",
+            "  https://example.com/~user/  ",
+        ] {
+            let base = BaseUrl::parse(raw);
+            assert_eq!(base.rel("css/style.css"), "/~user/css/style.css", "{raw}");
+            assert_eq!(
+                base.abs("css/style.css"),
+                "https://example.com/~user/css/style.css",
+                "{raw}"
+            );
+        }
+
+        let base = BaseUrl::parse("example.com/docs");
+        assert_eq!(base.abs("a.html"), "https://example.com/docs/a.html");
+    }
+
+    #[test]
+    fn path_only_base() {
+        let base = BaseUrl::parse("/~user/");
+        assert_eq!(base.rel("a.html"), "/~user/a.html");
+        assert_eq!(base.abs("a.html"), "/~user/a.html");
+
+        let base = BaseUrl::parse("");
+        assert_eq!(base.rel("a.html"), "/a.html");
+    }
+
+    #[test]
+    fn port_is_kept() {
+        let base = BaseUrl::parse("http://localhost:1313/");
+        assert_eq!(base.abs("a.html"), "http://localhost:1313/a.html");
+    }
+
+    #[test]
+    fn leading_slashes_and_dots() {
+        let base = BaseUrl::parse("https://example.com/sub/");
+        assert_eq!(base.rel("/a.html"), "/sub/a.html");
+        assert_eq!(base.rel("./a.html"), "/sub/a.html");
+        assert_eq!(base.rel("././a.html"), "/sub/a.html");
+        assert_eq!(base.rel(""), "/sub/");
+    }
+
+    #[test]
+    fn absolute_targets_are_left_alone() {
+        let base = BaseUrl::parse("https://example.com/sub/");
+        for target in [
+            "https://cdn.example.com/a.js",
+            "//cdn.example.com/a.js",
+            "mailto:you@example.com",
+            "tel:+1-555-0100",
+            "web+mastodon://share",
+        ] {
+            assert_eq!(base.rel(target), target);
+            assert_eq!(base.abs(target), target);
+        }
+    }
+
+    #[test]
+    fn colons_in_paths_are_not_schemes() {
+        assert!(!is_absolute("notes/12:30.html"));
+        assert!(!is_absolute("1password:x"));
+        assert!(!is_absolute(":x"));
+        assert!(is_absolute("HTTPS://example.com"));
+    }
+
+    #[test]
+    fn link_urls() {
+        for url in [
+            "https://example.com",
+            "/about",
+            "mailto:me@example.com",
+            "#top",
+        ] {
+            assert_eq!(check(url), Ok(()), "{url}");
+        }
+        assert!(check("").is_err());
+        assert!(check("https://example.com/a b").is_err());
+        assert!(check("https://").is_err());
+        assert!(check("https:///path").is_err());
+        assert!(check("https://?q").is_err());
+    }
+}
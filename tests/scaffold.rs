@@ -0,0 +1,301 @@
+/*
+    MLLT Simple Static Site Generator
+    Copyright (C) 2025 Nicolas "Montessquio" Suarez
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published
+    by the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Builds the sites `mllt new` creates, to make sure
+//! they render correctly out of the box.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Run mllt in `dir` and return what it printed, failing on errors.
+fn mllt(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_mllt"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("mllt should run");
+    assert!(
+        output.status.success(),
+        "`mllt {}` failed:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Run mllt in `dir`, expecting it to fail, and return its error output.
+fn mllt_fails(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_mllt"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("mllt should run");
+    assert!(
+        !output.status.success(),
+        "`mllt {}` succeeded",
+        args.join(" ")
+    );
+    String::from_utf8(output.stderr).unwrap()
+}
+
+/// An empty folder for one test to create sites in.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mllt-test-{name}-{}", std::process::id()));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Create a site with `theme`, build it in strict mode
+/// and return its index page.
+fn build_scaffold(scratch: &Path, theme: &str) -> String {
+    mllt(scratch, &["new", theme, "--theme", theme, "--yes"]);
+    let site = scratch.join(theme);
+    mllt(&site, &["build", "--strict"]);
+    std::fs::read_to_string(site.join("output/index.html")).unwrap()
+}
+
+#[test]
+fn default_scaffold_renders() {
+    let scratch = scratch_dir("default-scaffold");
+    let html = build_scaffold(&scratch, "minimal");
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.trim_end().ends_with("</html>"));
+    assert!(html.contains("<title>MLLT Example Site</title>"));
+
+    // Page content is HTML, not escaped text.
+    assert!(html.contains("<nav aria-label=\"Links\">"));
+    assert!(!html.contains("&lt;"));
+
+    // Every link from the default config, with its URL and nothing but its name.
+    for (url, name) in [
+        ("https://bsky.app/profile/example.bsky.social", "Bluesky"),
+        ("https://blog.example.com", "My Blog"),
+        ("https://github.com/example", "GitHub"),
+    ] {
+        assert!(
+            html.contains(&format!("href=\"{url}\"")),
+            "missing link to {url}"
+        );
+        assert!(
+            html.contains(&format!("<span>{name}</span>")),
+            "missing {name}"
+        );
+    }
+
+    assert!(html.contains("Made with <a href=\"https://github.com/Montessquio/mllt\">mllt</a>"));
+    for img in html.split("<img").skip(1) {
+        assert!(img.split('>').next().unwrap().contains("alt="));
+    }
+
+    let gitignore = std::fs::read_to_string(scratch.join("minimal/.gitignore")).unwrap();
+    assert!(gitignore.lines().any(|line| line == "/.mllt-cache/"));
+
+    std::fs::remove_dir_all(scratch).unwrap();
+}
+
+#[test]
+fn scaffold_builds_without_empty_folders() {
+    // What's left of a new site after a round trip through git.
+    let scratch = scratch_dir("empty-folders");
+    mllt(&scratch, &["new", "site", "--yes"]);
+    let site = scratch.join("site");
+    for dir in ["assets", "data", "layouts"] {
+        std::fs::remove_dir(site.join(dir)).unwrap();
+    }
+
+    mllt(&site, &["build", "--strict"]);
+    assert!(site.join("output/index.html").is_file());
+
+    std::fs::remove_dir_all(scratch).unwrap();
+}
+
+#[test]
+fn invalid_base_url_is_refused() {
+    let scratch = scratch_dir("invalid-base-url");
+    for args in [
+        &["new", "site", "--yes", "--base-url", "a b"][..],
+        &["new", "site", "--from", "template", "--base-url", "https://"],
+    ] {
+        let error = mllt_fails(&scratch, args);
+        assert!(error.contains("The base URL"), "{error}");
+        assert!(!scratch.join("site").exists());
+    }
+
+    std::fs::remove_dir_all(scratch).unwrap();
+}
+
+#[test]
+fn dry_run_leaves_the_output_alone() {
+    let scratch = scratch_dir("dry-run");
+    build_scaffold(&scratch, "minimal");
+    let site = scratch.join("minimal");
+    let index = site.join("output/index.html");
+    let before = std::fs::read_to_string(&index).unwrap();
+    std::fs::write(site.join("output/stale.html"), "").unwrap();
+    std::fs::write(site.join("content/index.hbs"), "edited").unwrap();
+
+    let log = mllt(&site, &["build", "--dry-run"]);
+    assert!(log.contains("Would remove: stale.html"), "{log}");
+    assert!(!log.contains("Would remove: index.html"), "{log}");
+    assert_eq!(std::fs::read_to_string(&index).unwrap(), before);
+    assert!(site.join("output/stale.html").is_file());
+
+    std::fs::remove_dir_all(scratch).unwrap();
+}
+
+#[test]
+fn every_starter_theme_renders() {
+    let scratch = scratch_dir("starter-themes");
+    let list = mllt(&scratch, &["theme", "list"]);
+    let themes: Vec<&str> = list
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    assert!(!themes.is_empty());
+
+    for theme in themes {
+        let html = build_scaffold(&scratch, theme);
+        assert!(
+            html.trim_end().ends_with("</html>"),
+            "{theme} isn't a whole page"
+        );
+        assert!(!html.contains("&lt;"), "{theme} escapes its content");
+        assert!(
+            html.contains("https://github.com/example"),
+            "{theme} lacks links"
+        );
+        for icon in html.split("<span class=\"icon\"").skip(1) {
+            assert!(
+                icon.split('>').next().unwrap().contains("aria-hidden=\"true\""),
+                "{theme} has an icon screen readers announce"
+            );
+        }
+    }
+
+    std::fs::remove_dir_all(scratch).unwrap();
+}
+
+#[test]
+fn site_from_local_template() {
+    let scratch = scratch_dir("local-template");
+    mllt(&scratch, &["new", "template", "--yes"]);
+    let template = scratch.join("template");
+    let config = std::fs::read_to_string(template.join("mllt.toml")).unwrap();
+    let config = config
+        .replace("\"example.com\"", "\"__BASE_URL__\"")
+        .replace("MLLT Example Site", "__PROJECT_NAME__");
+    std::fs::write(template.join("mllt.toml"), config).unwrap();
+
+    // Not valid UTF-8, so copied byte for byte.
+    let binary = b"\x89PNG\xff __PROJECT_NAME__".as_slice();
+    std::fs::create_dir_all(template.join("assets")).unwrap();
+    std::fs::write(template.join("assets/logo.png"), binary).unwrap();
+    std::fs::create_dir_all(template.join(".git")).unwrap();
+    std::fs::write(template.join(".git/HEAD"), "ref: refs/heads/main").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("/etc/passwd", template.join("assets/passwd")).unwrap();
+
+    let from = template.to_str().unwrap();
+    mllt(
+        &scratch,
+        &[
+            "new",
+            "site",
+            "--from",
+            from,
+            "--base-url",
+            "https://me.dev",
+        ],
+    );
+    let site = scratch.join("site");
+    let config = std::fs::read_to_string(site.join("mllt.toml")).unwrap();
+    assert!(config.contains("baseURL = \"https://me.dev\""));
+    assert!(config.contains("title = \"site\""));
+    assert_eq!(std::fs::read(site.join("assets/logo.png")).unwrap(), binary);
+    assert!(!site.join(".git").exists());
+    assert!(!site.join("assets/passwd").exists());
+
+    mllt(&site, &["build", "--strict"]);
+    let html = std::fs::read_to_string(site.join("output/index.html")).unwrap();
+    assert!(html.contains("<title>site</title>"));
+
+    // Only overwritten when forced.
+    std::fs::write(site.join("content/index.hbs"), "changed").unwrap();
+    let error = mllt_fails(&scratch, &["new", "site", "--from", from]);
+    assert!(error.contains("use `--force`"), "{error}");
+    assert_eq!(
+        std::fs::read_to_string(site.join("content/index.hbs")).unwrap(),
+        "changed"
+    );
+    mllt(&scratch, &["new", "site", "--from", from, "--force"]);
+    assert_eq!(
+        std::fs::read(site.join("content/index.hbs")).unwrap(),
+        std::fs::read(template.join("content/index.hbs")).unwrap()
+    );
+
+    let error = mllt_fails(&template, &["new", "sub", "--from", "."]);
+    assert!(error.contains("inside its own template"), "{error}");
+    assert!(!template.join("sub").exists());
+
+    std::fs::remove_dir_all(scratch).unwrap();
+}
+
+#[test]
+fn site_from_git_template() {
+    let git = |dir: &Path, args: &[&str]| {
+        Command::new("git")
+            .args(["-c", "user.name=mllt", "-c", "user.email=mllt@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .stdout(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+    let scratch = scratch_dir("git-template");
+    if !git(&scratch, &["--version"]) {
+        eprintln!("git isn't installed, skipping");
+        return;
+    }
+
+    mllt(&scratch, &["new", "template", "--yes"]);
+    let template = scratch.join("template");
+    for args in [
+        &["init", "--quiet"][..],
+        &["add", "-A"],
+        &["commit", "--quiet", "-m", "Template"],
+    ] {
+        assert!(git(&template, args));
+    }
+
+    let url = format!("file://{}", template.display());
+    mllt(&scratch, &["new", "site", "--from", &url]);
+    assert!(scratch.join("site/mllt.toml").is_file());
+    assert!(!scratch.join("site/.git").exists());
+
+    // Like local templates, only copied over a site when forced.
+    let error = mllt_fails(&scratch, &["new", "site", "--from", &url]);
+    assert!(error.contains("use `--force`"), "{error}");
+
+    std::fs::remove_dir_all(scratch).unwrap();
+}